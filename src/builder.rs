@@ -0,0 +1,379 @@
+//! This module provides the helper that fills an LR(1) parse table with actions.
+//!
+//! The builder is shared by the parse table constructions. It adds the shift and goto entries of
+//! an automaton and merges reductions into the table, resolving conflicts as configured by the
+//! `Config`.
+//!
+use crate::config::{ConflictWarner, PrecedenceResolution};
+use crate::{
+    Config, LR0State, LR1Conflict, LR1Conflicts, LR1Error, LR1ParseTable, LR1State, LRAction,
+    LRConflictResolution, PartialLR1ParseTable, Rhs, Symbol::*,
+};
+use std::cmp;
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::Infallible;
+
+/// The rules whose reductions were forbidden in each cell.
+type VetoedRules<'a, T, N, A> = BTreeMap<(usize, Option<&'a T>), Vec<&'a Rhs<T, N, A>>>;
+
+/// How a `TableBuilder` handles unresolved conflicts.
+pub(crate) trait ConflictMode<'a, T: 'a, N: 'a, A: 'a> {
+    /// The error that aborts the construction.
+    type Error;
+
+    /// Record an unresolved conflict in the cell of `state` and `token`, or return it as an error.
+    fn conflict(
+        &mut self,
+        state: usize,
+        token: Option<&'a T>,
+        conflict: LR1Conflict<'a, T, N, A>,
+    ) -> Result<(), Self::Error>;
+}
+
+/// Abort the construction on the first unresolved conflict.
+pub(crate) struct Abort;
+
+impl<'a, T: 'a, N: 'a, A: 'a> ConflictMode<'a, T, N, A> for Abort {
+    type Error = LR1Conflict<'a, T, N, A>;
+
+    fn conflict(
+        &mut self,
+        _state: usize,
+        _token: Option<&'a T>,
+        conflict: LR1Conflict<'a, T, N, A>,
+    ) -> Result<(), Self::Error> {
+        Err(conflict)
+    }
+}
+
+/// Collect all unresolved conflicts, so the construction never fails.
+pub(crate) struct Collect<'a, T: 'a, N: 'a, A: 'a>(LR1Conflicts<'a, T, N, A>);
+
+impl<'a, T: 'a, N: 'a, A: 'a> Collect<'a, T, N, A> {
+    pub fn new() -> Self {
+        Collect(BTreeMap::new())
+    }
+}
+
+impl<'a, T: Ord + 'a, N: Eq + 'a, A: 'a> ConflictMode<'a, T, N, A> for Collect<'a, T, N, A> {
+    type Error = Infallible;
+
+    fn conflict(
+        &mut self,
+        state: usize,
+        token: Option<&'a T>,
+        conflict: LR1Conflict<'a, T, N, A>,
+    ) -> Result<(), Self::Error> {
+        let cell = self.0.entry((state, token)).or_default();
+        // The same rule can end in the same state several times, so don't record a conflict
+        // twice.
+        if !cell.iter().any(|c| same_conflict(c, &conflict)) {
+            cell.push(conflict);
+        }
+        Ok(())
+    }
+}
+
+/// A helper for constructing a parse table.
+///
+/// The mode decides whether the builder aborts on the first unresolved conflict or collects all
+/// of them.
+pub(crate) struct TableBuilder<'a, 's, T: 'a, N: 'a, A: 'a, M = Abort> {
+    start: &'a N,
+    states: &'s [LR0State<'a, T, N, A>],
+    config: &'a dyn Config<'a, T, N, A>,
+    conflict_warner: ConflictWarner<'a, T, N, A>,
+    table: LR1ParseTable<'a, T, N, A>,
+    mode: M,
+    vetoed: VetoedRules<'a, T, N, A>,
+    resolved_shift_reduce: BTreeSet<(usize, Option<&'a T>)>,
+    resolved_reduce_reduce: BTreeSet<(usize, Option<&'a T>)>,
+}
+
+impl<'a, 's, T: Ord, N: Ord, A, M: ConflictMode<'a, T, N, A>> TableBuilder<'a, 's, T, N, A, M> {
+    /// Create a new builder and add the shift and goto entries for the given states.
+    pub fn new(
+        start: &'a N,
+        states: &'s [LR0State<'a, T, N, A>],
+        config: &'a dyn Config<'a, T, N, A>,
+        mode: M,
+    ) -> Self {
+        let mut table = LR1ParseTable {
+            states: states
+                .iter()
+                .map(|_| LR1State {
+                    eof: None,
+                    lookahead: BTreeMap::new(),
+                    goto: BTreeMap::new(),
+                })
+                .collect(),
+        };
+
+        // add shifts
+        for (i, (_, trans)) in states.iter().enumerate() {
+            for (&sym, &target) in trans.iter() {
+                match *sym {
+                    Terminal(ref t) => {
+                        let z = table.states[i].lookahead.insert(t, LRAction::Shift(target));
+                        // can't have conflicts yet
+                        debug_assert!(z.is_none());
+                    }
+                    Nonterminal(ref n) => {
+                        let z = table.states[i].goto.insert(n, target);
+                        debug_assert!(z.is_none());
+                    }
+                }
+            }
+        }
+
+        TableBuilder {
+            start,
+            states,
+            config,
+            conflict_warner: ConflictWarner::new(config),
+            table,
+            mode,
+            vetoed: BTreeMap::new(),
+            resolved_shift_reduce: BTreeSet::new(),
+            resolved_reduce_reduce: BTreeSet::new(),
+        }
+    }
+
+    /// Add a reduction by the rule `lhs -> rhs` in state `state` on the lookahead `token` (`None`
+    /// for EOF).
+    ///
+    /// The reduction is skipped if the configuration's `reduce_on` forbids it. If it would have
+    /// conflicted with another forbidden reduction and no other action ends up in the cell, the
+    /// cell gets an `LRAction::Error`. Conflicts with existing actions are
+    /// resolved as configured. An unresolved conflict is passed to the mode of the builder.
+    pub fn reduce(
+        &mut self,
+        state: usize,
+        token: Option<&'a T>,
+        lhs: &'a N,
+        rhs: &'a Rhs<T, N, A>,
+    ) -> Result<(), M::Error> {
+        if !self.config.reduce_on(rhs, token) {
+            let rules = self.vetoed.entry((state, token)).or_default();
+            if !rules.iter().any(|&r| std::ptr::eq(r, rhs)) {
+                rules.push(rhs);
+            }
+            return Ok(());
+        }
+        if token.is_none() && *lhs == *self.start {
+            let cell = &mut self.table.states[state].eof;
+            if cell.is_some() {
+                unreachable!()
+            }
+            *cell = Some(LRAction::Accept);
+            return Ok(());
+        }
+        let item_set = &self.states[state].0;
+        let mut conflict = None;
+        let action = match self.take(state, token) {
+            None => LRAction::Reduce(lhs, rhs),
+            Some(LRAction::Reduce(l, r)) if l == lhs && std::ptr::eq(r, rhs) => {
+                // The cells match, so there's no conflict.
+                LRAction::Reduce(l, r)
+            }
+            Some(LRAction::Reduce(l, r)) => {
+                match self
+                    .config
+                    .priority_of(r, token)
+                    .cmp(&self.config.priority_of(rhs, token))
+                {
+                    cmp::Ordering::Greater => {
+                        // `r` overrides `rhs` - do nothing.
+                        self.resolved_reduce_reduce.insert((state, token));
+                        self.conflict_warner.warn_reduce_reduce(
+                            item_set,
+                            token,
+                            (l, r),
+                            (lhs, rhs),
+                            LRConflictResolution::ReduceFirstRule,
+                        );
+                        LRAction::Reduce(l, r)
+                    }
+                    cmp::Ordering::Less => {
+                        // `rhs` overrides `r`.
+                        self.resolved_reduce_reduce.insert((state, token));
+                        self.conflict_warner.warn_reduce_reduce(
+                            item_set,
+                            token,
+                            (l, r),
+                            (lhs, rhs),
+                            LRConflictResolution::ReduceSecondRule,
+                        );
+                        LRAction::Reduce(lhs, rhs)
+                    }
+                    cmp::Ordering::Equal => {
+                        // Otherwise, we have a reduce/reduce conflict.
+                        conflict = Some(LR1Conflict::ReduceReduce {
+                            state: item_set.clone(),
+                            token,
+                            r1: (l, r),
+                            r2: (lhs, rhs),
+                        });
+                        LRAction::Reduce(l, r)
+                    }
+                }
+            }
+            Some(LRAction::Shift(target)) => {
+                // shift-reduce conflict
+                if let Some(resolution) = self.config.shift_reduce_precedence(lhs, rhs, token) {
+                    let (action, applied_resolution) = match resolution {
+                        PrecedenceResolution::Shift => (
+                            LRAction::Shift(target),
+                            LRConflictResolution::ShiftByPrecedence,
+                        ),
+                        PrecedenceResolution::Reduce => (
+                            LRAction::Reduce(lhs, rhs),
+                            LRConflictResolution::ReduceByPrecedence,
+                        ),
+                        PrecedenceResolution::Error => (
+                            LRAction::Error,
+                            LRConflictResolution::ErrorByNonassociativity,
+                        ),
+                    };
+                    self.conflict_warner.warn_shift_reduce(
+                        item_set,
+                        token,
+                        (lhs, rhs),
+                        applied_resolution,
+                    );
+                    action
+                } else if self
+                    .config
+                    .resolve_shift_reduce_conflict_in_favor_of_shift()
+                {
+                    // shift wins - do nothing
+                    self.resolved_shift_reduce.insert((state, token));
+                    self.conflict_warner.warn_shift_reduce(
+                        item_set,
+                        token,
+                        (lhs, rhs),
+                        LRConflictResolution::ShiftOverReduce,
+                    );
+                    LRAction::Shift(target)
+                } else {
+                    conflict = Some(LR1Conflict::ShiftReduce {
+                        state: item_set.clone(),
+                        token,
+                        rule: (lhs, rhs),
+                    });
+                    LRAction::Shift(target)
+                }
+            }
+            Some(LRAction::Error) => {
+                // A conflict resolution forbade this cell - keep the error.
+                LRAction::Error
+            }
+            Some(LRAction::Accept) => {
+                unreachable!();
+            }
+        };
+        self.put(state, token, action);
+        match conflict {
+            Some(conflict) => self.mode.conflict(state, token, conflict),
+            None => Ok(()),
+        }
+    }
+
+    /// Mark the cells whose conflicting reductions were all forbidden as errors, and return the
+    /// errors for the numbers of resolved conflicts that differ from the numbers expected by the
+    /// configuration.
+    fn complete(&mut self) -> Vec<LR1Error<'a, T, N, A>> {
+        let mut unexpected = vec![];
+        if let Some(expected) = self.config.expected_shift_reduce_conflicts() {
+            let found = self.resolved_shift_reduce.len();
+            if found != expected {
+                unexpected.push(LR1Error::UnexpectedShiftReduceConflicts { expected, found });
+            }
+        }
+        if let Some(expected) = self.config.expected_reduce_reduce_conflicts() {
+            let found = self.resolved_reduce_reduce.len();
+            if found != expected {
+                unexpected.push(LR1Error::UnexpectedReduceReduceConflicts { expected, found });
+            }
+        }
+        for ((state, token), rules) in std::mem::take(&mut self.vetoed) {
+            // If conflicting reductions in this cell were all forbidden, mark it as an error.
+            if rules.len() > 1 {
+                let action = self.take(state, token).unwrap_or(LRAction::Error);
+                self.put(state, token, action);
+            }
+        }
+        unexpected
+    }
+
+    /// Remove the action for the given lookahead from a state.
+    fn take(&mut self, state: usize, token: Option<&'a T>) -> Option<LRAction<'a, T, N, A>> {
+        let state = &mut self.table.states[state];
+        match token {
+            Some(t) => state.lookahead.remove(t),
+            None => state.eof.take(),
+        }
+    }
+
+    /// Set the action for the given lookahead in a state.
+    fn put(&mut self, state: usize, token: Option<&'a T>, action: LRAction<'a, T, N, A>) {
+        let state = &mut self.table.states[state];
+        match token {
+            Some(t) => {
+                state.lookahead.insert(t, action);
+            }
+            None => state.eof = Some(action),
+        }
+    }
+}
+
+impl<'a, 's, T: Ord, N: Ord, A> TableBuilder<'a, 's, T, N, A, Abort> {
+    /// Finish the construction.
+    ///
+    /// Fails if the numbers of resolved conflicts differ from the numbers expected by the
+    /// configuration.
+    pub fn finish(mut self) -> Result<LR1ParseTable<'a, T, N, A>, LR1Error<'a, T, N, A>> {
+        match self.complete().into_iter().next() {
+            Some(error) => Err(error),
+            None => Ok(self.table),
+        }
+    }
+}
+
+impl<'a, 's, T: Ord, N: Ord, A> TableBuilder<'a, 's, T, N, A, Collect<'a, T, N, A>> {
+    /// Finish the construction, returning the table with the collected conflicts and the errors
+    /// for the numbers of resolved conflicts that differ from the numbers expected by the
+    /// configuration.
+    pub fn finish(mut self) -> PartialLR1ParseTable<'a, T, N, A> {
+        let unexpected = self.complete();
+        PartialLR1ParseTable {
+            table: self.table,
+            conflicts: self.mode.0,
+            unexpected,
+        }
+    }
+}
+
+/// Check whether two conflicts in the same cell involve the same rules.
+fn same_conflict<T, N: Eq, A>(c1: &LR1Conflict<T, N, A>, c2: &LR1Conflict<T, N, A>) -> bool {
+    fn same_rule<T, N: Eq, A>(r1: (&N, &Rhs<T, N, A>), r2: (&N, &Rhs<T, N, A>)) -> bool {
+        r1.0 == r2.0 && std::ptr::eq(r1.1, r2.1)
+    }
+    match (c1, c2) {
+        (
+            LR1Conflict::ReduceReduce { r1, r2, .. },
+            LR1Conflict::ReduceReduce {
+                r1: other1,
+                r2: other2,
+                ..
+            },
+        ) => same_rule(*r1, *other1) && same_rule(*r2, *other2),
+        (
+            LR1Conflict::ShiftReduce { rule, .. },
+            LR1Conflict::ShiftReduce {
+                rule: other_rule, ..
+            },
+        ) => same_rule(*rule, *other_rule),
+        _ => false,
+    }
+}
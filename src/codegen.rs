@@ -0,0 +1,219 @@
+//! This module provides a generator of Rust source code for parse tables, and the runtime for the
+//! generated tables.
+//!
+//! [`write_parse_table`](fn.write_parse_table.html) writes a module with a
+//! [`StaticParseTable`](struct.StaticParseTable.html) in `static` arrays. A build script can
+//! write it to `OUT_DIR`, so the table is built at compile time instead of on every start-up:
+//!
+//! ```ignore
+//! // build.rs
+//! let table = grammar.lalr1(&config).unwrap();
+//! let mut source = String::new();
+//! lalry::codegen::write_parse_table(&mut source, "calc", &grammar, &table).unwrap();
+//! std::fs::write(Path::new(&env::var("OUT_DIR").unwrap()).join("calc.rs"), source).unwrap();
+//!
+//! // src/main.rs
+//! include!(concat!(env!("OUT_DIR"), "/calc.rs"));
+//! let value = calc::TABLE.parse(tokens, terminal_index, shift, reduce);
+//! ```
+//!
+//! The runtime only needs the static arrays, not the `Grammar` or any map. The terminals,
+//! nonterminals and rules are numbered as in an
+//! [`IndexedParseTable`](../indexed/struct.IndexedParseTable.html).
+//!
+use crate::indexed::{index_table, parse_indexed, IndexedAction, IndexedLookup};
+use crate::parser::SyntaxError;
+use crate::{Grammar, LR1ParseTable, Symbol};
+use std::fmt::{self, Display, Write};
+
+/// A rule of a static parse table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StaticRule {
+    /// The index of the nonterminal on the left-hand side.
+    pub lhs: usize,
+    /// The number of symbols on the right-hand side.
+    pub len: usize,
+}
+
+/// A parse table in static arrays, as written by
+/// [`write_parse_table`](fn.write_parse_table.html).
+#[derive(Debug, Clone, Copy)]
+pub struct StaticParseTable<'s> {
+    /// The names of the terminals.
+    pub terminals: &'s [&'s str],
+    /// The names of the nonterminals.
+    pub nonterminals: &'s [&'s str],
+    /// The rules.
+    pub rules: &'s [StaticRule],
+    /// The actions, by state and then by terminal, with a last column for EOF.
+    pub actions: &'s [Option<IndexedAction>],
+    /// The state to jump to, by state and then by nonterminal.
+    pub gotos: &'s [Option<usize>],
+}
+
+impl<'s> StaticParseTable<'s> {
+    /// Return the number of states.
+    pub fn len(&self) -> usize {
+        self.actions.len() / (self.terminals.len() + 1)
+    }
+
+    /// Check whether the table has no states.
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+
+    /// Return the index of the terminal with the given name, if it is in the table.
+    pub fn terminal_index(&self, name: &str) -> Option<usize> {
+        self.terminals.iter().position(|&t| t == name)
+    }
+
+    /// Return the action in a state for the terminal with the given index (`None` for EOF), if
+    /// any.
+    pub fn action(&self, state: usize, terminal: Option<usize>) -> Option<IndexedAction> {
+        let width = self.terminals.len() + 1;
+        self.actions[state * width + terminal.unwrap_or(width - 1)]
+    }
+
+    /// Return the state to jump to from a state after reducing to the nonterminal with the given
+    /// index.
+    pub fn goto(&self, state: usize, nonterminal: usize) -> Option<usize> {
+        self.gotos[state * self.nonterminals.len() + nonterminal]
+    }
+
+    /// Parse a sequence of tokens.
+    ///
+    /// `terminal` maps each token to the index of its terminal, or `None` if it is not a terminal
+    /// of the table. `shift` turns a shifted token into a value, and `reduce` computes the value
+    /// of a rule from its index and the values of the symbols of its right-hand side. Returns the
+    /// value of the symbol of the start rule, or the first syntax error. The start rule is never
+    /// reduced, so it has no index.
+    pub fn parse<Tok, V, I, L, S, R>(
+        &self,
+        tokens: I,
+        terminal: L,
+        shift: S,
+        reduce: R,
+    ) -> Result<V, SyntaxError<Tok>>
+    where
+        I: IntoIterator<Item = Tok>,
+        L: Fn(&Tok) -> Option<usize>,
+        S: FnMut(Tok) -> V,
+        R: FnMut(usize, Vec<V>) -> V,
+    {
+        parse_indexed(self, tokens, terminal, shift, reduce)
+    }
+}
+
+impl<'s> IndexedLookup for StaticParseTable<'s> {
+    fn action(&self, state: usize, terminal: Option<usize>) -> Option<IndexedAction> {
+        StaticParseTable::action(self, state, terminal)
+    }
+
+    fn goto(&self, state: usize, nonterminal: usize) -> usize {
+        StaticParseTable::goto(self, state, nonterminal).unwrap()
+    }
+
+    fn rule(&self, rule: usize) -> (usize, usize) {
+        (self.rules[rule].lhs, self.rules[rule].len)
+    }
+}
+
+/// Write the Rust source of a module named `module` with a parse table constructed from the
+/// grammar in a `pub static TABLE: StaticParseTable`.
+///
+/// The source refers to this crate as `::lalry`. The names of the terminals and nonterminals are
+/// their `Display` forms. The actions of the rules are not written: `StaticParseTable::parse`
+/// passes the index of a rule to `reduce`, and the rules are numbered in the order of the grammar
+/// without the start rule, by left-hand side and then in the order of the right-hand sides, as in
+/// [`IndexedParseTable::new`](../indexed/struct.IndexedParseTable.html#method.new). The source
+/// has a comment with the index and the symbols of each rule.
+pub fn write_parse_table<'a, W, T, N, A>(
+    out: &mut W,
+    module: &str,
+    grammar: &'a Grammar<T, N, A>,
+    table: &LR1ParseTable<'a, T, N, A>,
+) -> fmt::Result
+where
+    W: Write,
+    T: Ord + Clone + Display,
+    N: Ord + Clone + Display,
+{
+    let table = index_table(grammar, table, |_| ());
+    writeln!(out, "/// The parse table generated by lalry.")?;
+    writeln!(out, "#[allow(dead_code)]")?;
+    writeln!(out, "pub mod {} {{", module)?;
+    writeln!(
+        out,
+        "    use ::lalry::codegen::{{StaticParseTable, StaticRule}};"
+    )?;
+    writeln!(out, "    use ::lalry::indexed::IndexedAction::*;")?;
+    writeln!(out)?;
+    writeln!(out, "    /// The parse table.")?;
+    writeln!(
+        out,
+        "    pub static TABLE: StaticParseTable<'static> = StaticParseTable {{"
+    )?;
+    write_names(out, "terminals", &table.terminals)?;
+    write_names(out, "nonterminals", &table.nonterminals)?;
+
+    writeln!(out, "        rules: &[")?;
+    for (i, rule) in table.rules.iter().enumerate() {
+        write!(
+            out,
+            "            StaticRule {{ lhs: {}, len: {} }}, // {}: {} →",
+            rule.lhs, rule.len, i, table.nonterminals[rule.lhs]
+        )?;
+        for sym in rule.syms.iter() {
+            match *sym {
+                Symbol::Terminal(t) => write!(out, " {}", table.terminals[t])?,
+                Symbol::Nonterminal(n) => write!(out, " {}", table.nonterminals[n])?,
+            }
+        }
+        writeln!(out)?;
+    }
+    writeln!(out, "        ],")?;
+
+    writeln!(out, "        actions: &[")?;
+    for (state, actions) in table.actions.iter().enumerate() {
+        writeln!(out, "            // state {}", state)?;
+        write!(out, "           ")?;
+        for action in actions.iter() {
+            match *action {
+                Some(IndexedAction::Shift(target)) => write!(out, " Some(Shift({})),", target)?,
+                Some(IndexedAction::Reduce(rule)) => write!(out, " Some(Reduce({})),", rule)?,
+                Some(IndexedAction::Accept) => write!(out, " Some(Accept),")?,
+                Some(IndexedAction::Error) => write!(out, " Some(Error),")?,
+                None => write!(out, " None,")?,
+            }
+        }
+        writeln!(out)?;
+    }
+    writeln!(out, "        ],")?;
+
+    writeln!(out, "        gotos: &[")?;
+    for gotos in table.gotos.iter() {
+        write!(out, "           ")?;
+        for goto in gotos.iter() {
+            match *goto {
+                Some(target) => write!(out, " Some({}),", target)?,
+                None => write!(out, " None,")?,
+            }
+        }
+        writeln!(out)?;
+    }
+    writeln!(out, "        ],")?;
+    writeln!(out, "    }};")?;
+    writeln!(out, "}}")
+}
+
+/// Write a field with the names of symbols as string literals.
+fn write_names<W: Write, S: Display>(out: &mut W, field: &str, symbols: &[S]) -> fmt::Result {
+    write!(out, "        {}: &[", field)?;
+    for (i, sym) in symbols.iter().enumerate() {
+        if i > 0 {
+            write!(out, ", ")?;
+        }
+        write!(out, "{:?}", sym.to_string())?;
+    }
+    writeln!(out, "],")
+}
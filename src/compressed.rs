@@ -0,0 +1,320 @@
+//! This module provides a compressed parse table, which packs the actions and gotos into a few
+//! flat integer arrays in the style of Bison.
+//!
+//! Each state has a default action: its most frequent reduction, or an error if it has none. The
+//! actions that differ from the default are the entries of a sparse row, and the gotos of each
+//! nonterminal, except its most frequent target state, are the entries of another sparse row. The
+//! rows are overlaid in a single `table` by the comb-vector (row displacement) method: each row
+//! has a distinct `base`, its entry for the key `k` is at `table[base + k]`, and `check[base + k]`
+//! holds `k` to tell it from the entries of the other rows. A lookup takes constant time.
+//!
+//! A default reduction replaces the errors of its state, so a syntax error may be detected after
+//! a few more reductions than with the [`IndexedParseTable`](../indexed/struct.IndexedParseTable.html),
+//! but always before the erroneous token is shifted.
+//!
+use crate::indexed::{parse_indexed, IndexedAction, IndexedLookup, IndexedParseTable, IndexedRule};
+use crate::parser::SyntaxError;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Display};
+
+/// The encoding of an error action.
+const ERROR: i32 = 0;
+
+/// The encoding of the accept action.
+const ACCEPT: i32 = i32::MIN;
+
+/// The check of an unused entry of the table.
+const EMPTY: u32 = u32::MAX;
+
+/// Encode an action: a shift to state `s` (never the initial state) as `s`, a reduction by rule
+/// `r` as `-(r + 1)`.
+fn encode(action: IndexedAction) -> i32 {
+    match action {
+        IndexedAction::Shift(target) => target as i32,
+        IndexedAction::Reduce(rule) => -(rule as i32) - 1,
+        IndexedAction::Accept => ACCEPT,
+        IndexedAction::Error => ERROR,
+    }
+}
+
+/// Decode an action.
+fn decode(action: i32) -> IndexedAction {
+    match action {
+        ERROR => IndexedAction::Error,
+        ACCEPT => IndexedAction::Accept,
+        a if a > 0 => IndexedAction::Shift(a as usize),
+        a => IndexedAction::Reduce((-a - 1) as usize),
+    }
+}
+
+/// The size of a compressed parse table, compared with the dense table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Compression {
+    /// The number of entries of the dense action and goto tables.
+    pub dense: usize,
+    /// The number of integers of the compressed table.
+    pub compressed: usize,
+}
+
+impl Compression {
+    /// Return the size of the compressed table relative to the dense table.
+    pub fn ratio(&self) -> f64 {
+        self.compressed as f64 / self.dense as f64
+    }
+}
+
+impl Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} entries compressed to {} ({:.1}%)",
+            self.dense,
+            self.compressed,
+            100.0 * self.ratio()
+        )
+    }
+}
+
+/// A parse table packed into flat integer arrays.
+///
+/// The actions are encoded as `0` for an error, `s > 0` for a shift to state `s`, `-(r + 1)` for
+/// a reduction by rule `r`, and `i32::MIN` to accept.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CompressedParseTable<T, N, A> {
+    /// The terminals, in increasing order.
+    pub terminals: Vec<T>,
+    /// The nonterminals, in increasing order.
+    pub nonterminals: Vec<N>,
+    /// The rules.
+    pub rules: Vec<IndexedRule<A>>,
+    /// The default action of each state, an error or a reduction.
+    pub default_actions: Vec<i32>,
+    /// The base of the row of actions of each state, indexed by terminal, with EOF after the
+    /// terminals.
+    pub action_bases: Vec<u32>,
+    /// The default state to jump to after reducing to each nonterminal.
+    pub default_gotos: Vec<u32>,
+    /// The base of the row of gotos of each nonterminal, indexed by state.
+    pub goto_bases: Vec<u32>,
+    /// The entries of the rows: encoded actions, and states to jump to.
+    pub table: Vec<i32>,
+    /// The key of each entry of `table`, `u32::MAX` for an unused one.
+    pub check: Vec<u32>,
+}
+
+impl<T: Ord, N, A> CompressedParseTable<T, N, A> {
+    /// Return the number of states.
+    pub fn len(&self) -> usize {
+        self.action_bases.len()
+    }
+
+    /// Check whether the table has no states.
+    pub fn is_empty(&self) -> bool {
+        self.action_bases.is_empty()
+    }
+
+    /// Return the index of a terminal, if it is in the table.
+    pub fn terminal_index(&self, terminal: &T) -> Option<usize> {
+        self.terminals.binary_search(terminal).ok()
+    }
+
+    /// Look up the entry with the given key in the row with the given base.
+    fn lookup(&self, base: u32, key: usize) -> Option<i32> {
+        let i = base as usize + key;
+        if self.check.get(i) == Some(&(key as u32)) {
+            Some(self.table[i])
+        } else {
+            None
+        }
+    }
+
+    /// Return the action in a state for the terminal with the given index (`None` for EOF).
+    pub fn action(&self, state: usize, terminal: Option<usize>) -> IndexedAction {
+        let key = terminal.unwrap_or(self.terminals.len());
+        let action = self
+            .lookup(self.action_bases[state], key)
+            .unwrap_or(self.default_actions[state]);
+        decode(action)
+    }
+
+    /// Return the state to jump to from a state after reducing to the nonterminal with the given
+    /// index.
+    ///
+    /// The result is only meaningful if the state has a goto on the nonterminal, which is always
+    /// the case after a reduction of the parse.
+    pub fn goto(&self, state: usize, nonterminal: usize) -> usize {
+        self.lookup(self.goto_bases[nonterminal], state)
+            .map_or(self.default_gotos[nonterminal] as usize, |target| {
+                target as usize
+            })
+    }
+
+    /// Return the size of the table compared with the dense table.
+    pub fn compression(&self) -> Compression {
+        let width = self.terminals.len() + 1 + self.nonterminals.len();
+        Compression {
+            dense: self.len() * width,
+            compressed: self.default_actions.len()
+                + self.action_bases.len()
+                + self.default_gotos.len()
+                + self.goto_bases.len()
+                + self.table.len()
+                + self.check.len(),
+        }
+    }
+
+    /// Parse a sequence of tokens, as
+    /// [`IndexedParseTable::parse`](../indexed/struct.IndexedParseTable.html#method.parse).
+    pub fn parse<Tok, V, I, L, S, R>(
+        &self,
+        tokens: I,
+        terminal: L,
+        shift: S,
+        mut reduce: R,
+    ) -> Result<V, SyntaxError<Tok>>
+    where
+        I: IntoIterator<Item = Tok>,
+        L: Fn(&Tok) -> T,
+        S: FnMut(Tok) -> V,
+        R: FnMut(&A, Vec<V>) -> V,
+    {
+        parse_indexed(
+            self,
+            tokens,
+            |token| self.terminal_index(&terminal(token)),
+            shift,
+            |rule, children| reduce(&self.rules[rule].act, children),
+        )
+    }
+}
+
+impl<T: Ord, N, A> IndexedLookup for CompressedParseTable<T, N, A> {
+    fn action(&self, state: usize, terminal: Option<usize>) -> Option<IndexedAction> {
+        Some(CompressedParseTable::action(self, state, terminal))
+    }
+
+    fn goto(&self, state: usize, nonterminal: usize) -> usize {
+        CompressedParseTable::goto(self, state, nonterminal)
+    }
+
+    fn rule(&self, rule: usize) -> (usize, usize) {
+        (self.rules[rule].lhs, self.rules[rule].len)
+    }
+}
+
+/// Return the most frequent of the values, the first one among the most frequent.
+fn most_frequent<I: Iterator<Item = i32>>(values: I) -> Option<i32> {
+    let mut counts = BTreeMap::new();
+    let mut order = vec![];
+    for value in values {
+        *counts.entry(value).or_insert_with(|| {
+            order.push(value);
+            0
+        }) += 1;
+    }
+    order.into_iter().rev().max_by_key(|v| counts[v])
+}
+
+/// Overlay the rows of `(key, value)` entries in the table, and return the base of each row.
+///
+/// The rows are placed from the largest to the smallest, each at the lowest base that is not the
+/// base of another row and where its entries are unused.
+fn pack(rows: &[Vec<(usize, i32)>], table: &mut Vec<i32>, check: &mut Vec<u32>) -> Vec<u32> {
+    let mut order: Vec<_> = (0..rows.len()).collect();
+    order.sort_by_key(|&i| Reverse(rows[i].len()));
+    let mut bases = vec![0; rows.len()];
+    let mut used = BTreeSet::new();
+    for i in order {
+        let row = &rows[i];
+        let base = (0..)
+            .find(|base| {
+                !used.contains(base)
+                    && row
+                        .iter()
+                        .all(|&(key, _)| !matches!(check.get(base + key), Some(&c) if c != EMPTY))
+            })
+            .unwrap();
+        used.insert(base);
+        for &(key, value) in row {
+            if table.len() <= base + key {
+                table.resize(base + key + 1, ERROR);
+                check.resize(base + key + 1, EMPTY);
+            }
+            table[base + key] = value;
+            check[base + key] = key as u32;
+        }
+        bases[i] = base as u32;
+    }
+    bases
+}
+
+impl<T, N, A> From<IndexedParseTable<T, N, A>> for CompressedParseTable<T, N, A> {
+    /// Compress a parse table.
+    fn from(indexed: IndexedParseTable<T, N, A>) -> Self {
+        let mut default_actions = vec![];
+        let mut rows = vec![];
+        for actions in indexed.actions.iter() {
+            let reductions = actions.iter().filter_map(|&action| match action {
+                Some(IndexedAction::Reduce(rule)) => Some(encode(IndexedAction::Reduce(rule))),
+                _ => None,
+            });
+            let default = most_frequent(reductions).unwrap_or(ERROR);
+            default_actions.push(default);
+            rows.push(
+                actions
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(key, &action)| {
+                        let action = encode(action?);
+                        if action == default {
+                            None
+                        } else {
+                            Some((key, action))
+                        }
+                    })
+                    .collect(),
+            );
+        }
+
+        let mut default_gotos = vec![];
+        for nonterminal in 0..indexed.nonterminals.len() {
+            let targets = indexed
+                .gotos
+                .iter()
+                .filter_map(|gotos| gotos[nonterminal].map(|target| target as i32));
+            let default = most_frequent(targets).unwrap_or(0);
+            default_gotos.push(default as u32);
+            rows.push(
+                indexed
+                    .gotos
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(state, gotos)| match gotos[nonterminal] {
+                        Some(target) if target as i32 != default => Some((state, target as i32)),
+                        _ => None,
+                    })
+                    .collect(),
+            );
+        }
+
+        let mut table = vec![];
+        let mut check = vec![];
+        let mut bases = pack(&rows, &mut table, &mut check);
+        let goto_bases = bases.split_off(indexed.actions.len());
+        CompressedParseTable {
+            terminals: indexed.terminals,
+            nonterminals: indexed.nonterminals,
+            rules: indexed.rules,
+            default_actions,
+            action_bases: bases,
+            default_gotos,
+            goto_bases,
+            table,
+            check,
+        }
+    }
+}
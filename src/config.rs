@@ -3,7 +3,8 @@
 //! The user can implement this trait to provide a custom configuration.
 //! The default configuration is provided by the default implementation of this trait.
 //!
-use crate::{ItemSet, LR1Conflict, LR1ResolvedConflict, LRConflictResolution, Rhs};
+use crate::{ItemSet, LR1Conflict, LR1ResolvedConflict, LRConflictResolution, Rhs, Symbol};
+use std::collections::BTreeMap;
 
 /// The trait for configuration.
 pub trait Config<'a, T, N, A> {
@@ -64,6 +65,84 @@ pub trait Config<'a, T, N, A> {
     fn priority_of(&self, _rhs: &Rhs<T, N, A>, _lookahead: Option<&T>) -> i32 {
         0
     }
+
+    /// `shift_reduce_precedence` allows you to resolve shift-reduce conflicts by the precedence
+    /// of the rule and the lookahead token, like the `%left`, `%right`, `%nonassoc` and `%prec`
+    /// declarations of Yacc and Bison. This takes the rule, given by its left-hand side and its
+    /// right-hand side, and the lookahead token (or `None` for EOF).
+    ///
+    /// If this returns `None`, the conflict is handled according to
+    /// `resolve_shift_reduce_conflict_in_favor_of_shift`. This is the default behavior.
+    /// `PrecedenceConfig` provides an implementation based on precedence declarations.
+    fn shift_reduce_precedence(
+        &self,
+        _lhs: &N,
+        _rhs: &Rhs<T, N, A>,
+        _lookahead: Option<&T>,
+    ) -> Option<PrecedenceResolution> {
+        None
+    }
+
+    /// `expected_shift_reduce_conflicts` returns the number of shift-reduce conflicts that are
+    /// expected to be resolved in favor of shift, like the `%expect` declaration of Bison.
+    /// Conflicts resolved by `shift_reduce_precedence` are not counted. Each pair of a state and a
+    /// lookahead token counts once.
+    ///
+    /// If the number of resolved conflicts differs, the parse table generation fails with
+    /// `LR1Error::UnexpectedShiftReduceConflicts`. This way a change of the grammar can't
+    /// silently add a new conflict. If this method returns `None`, the number is not checked.
+    /// This is the default behavior.
+    fn expected_shift_reduce_conflicts(&self) -> Option<usize> {
+        None
+    }
+
+    /// `expected_reduce_reduce_conflicts` returns the number of reduce-reduce conflicts that are
+    /// expected to be resolved by `priority_of`, like the `%expect-rr` declaration of Bison.
+    /// Each pair of a state and a lookahead token counts once.
+    ///
+    /// If the number of resolved conflicts differs, the parse table generation fails with
+    /// `LR1Error::UnexpectedReduceReduceConflicts`. If this method returns `None`, the number is
+    /// not checked. This is the default behavior.
+    fn expected_reduce_reduce_conflicts(&self) -> Option<usize> {
+        None
+    }
+
+    /// `lalr1_lookaheads` selects the algorithm that computes the lookaheads of the LALR(1)
+    /// construction. Both algorithms produce the same parse table.
+    ///
+    /// The default is `LalrLookaheads::ExtendedGrammar`. `LalrLookaheads::DeRemerPennello` is
+    /// considerably faster for large grammars.
+    fn lalr1_lookaheads(&self) -> LalrLookaheads {
+        LalrLookaheads::ExtendedGrammar
+    }
+}
+
+/// The resolution of a shift-reduce conflict by precedence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrecedenceResolution {
+    /// Shift the lookahead token.
+    Shift,
+    /// Reduce by the rule.
+    Reduce,
+    /// Report a syntax error, see `LRAction::Error`.
+    Error,
+}
+
+/// The algorithm used to compute the lookaheads of the LALR(1) construction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LalrLookaheads {
+    /// Compute the FOLLOW sets of the LALR(1) extended grammar, see
+    /// [`LR0StateMachine::extended_grammar`](../struct.LR0StateMachine.html#method.extended_grammar).
+    ExtendedGrammar,
+    /// Compute the lookaheads on the LR(0) state machine with the relations of DeRemer and
+    /// Pennello.
+    DeRemerPennello,
+}
+
+impl Default for LalrLookaheads {
+    fn default() -> Self {
+        LalrLookaheads::ExtendedGrammar
+    }
 }
 
 /// The default configuration.
@@ -93,6 +172,188 @@ impl<'a, T, N, A> Default for DefaultConfig<'a, T, N, A> {
 /// The implementation of the configuration trait for the default configuration.
 impl<'a, T, N, A> Config<'a, T, N, A> for DefaultConfig<'a, T, N, A> {}
 
+// -----------------------------------------------------------------------------------------------
+// PrecedenceConfig
+// -----------------------------------------------------------------------------------------------
+
+/// The associativity of a precedence level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Associativity {
+    /// Left-associative, like `%left`: a conflict between tokens of the same level is resolved by
+    /// reducing.
+    Left,
+    /// Right-associative, like `%right`: a conflict between tokens of the same level is resolved
+    /// by shifting.
+    Right,
+    /// Nonassociative, like `%nonassoc`: a conflict between tokens of the same level is resolved
+    /// by an error action.
+    NonAssoc,
+}
+
+/// The overridden precedences: the right-hand sides and their tokens for each left-hand side.
+type RulePrecedences<T, N> = BTreeMap<N, Vec<(Vec<Symbol<T, N>>, T)>>;
+
+/// A configuration that resolves shift-reduce conflicts by precedence declarations in the style
+/// of Yacc and Bison.
+///
+/// Each call of [`left`](#method.left), [`right`](#method.right) or
+/// [`nonassoc`](#method.nonassoc) declares a precedence level for the given tokens, with a higher
+/// precedence than the levels declared before. The precedence of a rule is the precedence of its
+/// last terminal, unless it was overridden with [`rule_precedence`](#method.rule_precedence),
+/// like `%prec`.
+///
+/// A shift-reduce conflict is resolved if both the rule and the lookahead token have a
+/// precedence. The action with the higher precedence wins. On the same level, the associativity
+/// decides. Other conflicts are handled as by the default configuration, unless
+/// [`shift_by_default`](#method.shift_by_default) is enabled.
+///
+/// ```ignore
+/// let config = PrecedenceConfig::new()
+///     .left(vec!["+", "-"])
+///     .left(vec!["*", "/"])
+///     .right(vec!["UMINUS"])
+///     .rule_precedence("Expr", vec![Terminal("-"), Nonterminal("Expr")], "UMINUS");
+/// let parse_table = grammar.lalr1(&config).unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct PrecedenceConfig<T, N> {
+    levels: BTreeMap<T, (usize, Associativity)>,
+    rule_precedences: RulePrecedences<T, N>,
+    shift_by_default: bool,
+    expected_shift_reduce: Option<usize>,
+    expected_reduce_reduce: Option<usize>,
+}
+
+impl<T: Ord, N: Ord> PrecedenceConfig<T, N> {
+    /// Create a new configuration without precedence declarations.
+    pub fn new() -> Self {
+        PrecedenceConfig {
+            levels: BTreeMap::new(),
+            rule_precedences: BTreeMap::new(),
+            shift_by_default: false,
+            expected_shift_reduce: None,
+            expected_reduce_reduce: None,
+        }
+    }
+
+    /// Declare a precedence level of left-associative tokens, like `%left`.
+    pub fn left(self, tokens: impl IntoIterator<Item = T>) -> Self {
+        self.level(tokens, Associativity::Left)
+    }
+
+    /// Declare a precedence level of right-associative tokens, like `%right`.
+    pub fn right(self, tokens: impl IntoIterator<Item = T>) -> Self {
+        self.level(tokens, Associativity::Right)
+    }
+
+    /// Declare a precedence level of nonassociative tokens, like `%nonassoc`.
+    pub fn nonassoc(self, tokens: impl IntoIterator<Item = T>) -> Self {
+        self.level(tokens, Associativity::NonAssoc)
+    }
+
+    /// Declare a precedence level with the given associativity.
+    pub fn level(mut self, tokens: impl IntoIterator<Item = T>, assoc: Associativity) -> Self {
+        let level = self
+            .levels
+            .values()
+            .map(|&(level, _)| level + 1)
+            .max()
+            .unwrap_or(0);
+        for token in tokens {
+            self.levels.insert(token, (level, assoc));
+        }
+        self
+    }
+
+    /// Give the rule `lhs -> syms` the precedence of `token`, like `%prec`.
+    ///
+    /// The token doesn't need to appear in the grammar, but it needs a precedence level.
+    pub fn rule_precedence(mut self, lhs: N, syms: Vec<Symbol<T, N>>, token: T) -> Self {
+        self.rule_precedences
+            .entry(lhs)
+            .or_default()
+            .push((syms, token));
+        self
+    }
+
+    /// Resolve the shift-reduce conflicts that can't be resolved by precedence in favor of shift,
+    /// see `Config::resolve_shift_reduce_conflict_in_favor_of_shift`.
+    pub fn shift_by_default(mut self, shift_by_default: bool) -> Self {
+        self.shift_by_default = shift_by_default;
+        self
+    }
+
+    /// Expect the given number of shift-reduce conflicts resolved in favor of shift, like
+    /// `%expect`, see `Config::expected_shift_reduce_conflicts`.
+    pub fn expect(mut self, conflicts: usize) -> Self {
+        self.expected_shift_reduce = Some(conflicts);
+        self
+    }
+
+    /// Expect the given number of resolved reduce-reduce conflicts, like `%expect-rr`, see
+    /// `Config::expected_reduce_reduce_conflicts`.
+    pub fn expect_rr(mut self, conflicts: usize) -> Self {
+        self.expected_reduce_reduce = Some(conflicts);
+        self
+    }
+
+    /// Return the precedence level of a rule, if any.
+    fn rule_level<A>(&self, lhs: &N, rhs: &Rhs<T, N, A>) -> Option<(usize, Associativity)> {
+        let overridden = self
+            .rule_precedences
+            .get(lhs)
+            .and_then(|rules| rules.iter().find(|(syms, _)| *syms == rhs.syms))
+            .map(|(_, token)| token);
+        let token = overridden.or_else(|| {
+            rhs.syms.iter().rev().find_map(|sym| match *sym {
+                Symbol::Terminal(ref t) => Some(t),
+                Symbol::Nonterminal(_) => None,
+            })
+        })?;
+        self.levels.get(token).cloned()
+    }
+}
+
+impl<T: Ord, N: Ord> Default for PrecedenceConfig<T, N> {
+    fn default() -> Self {
+        PrecedenceConfig::new()
+    }
+}
+
+/// The implementation of the configuration trait for the precedence configuration.
+impl<'a, T: Ord, N: Ord, A> Config<'a, T, N, A> for PrecedenceConfig<T, N> {
+    fn resolve_shift_reduce_conflict_in_favor_of_shift(&self) -> bool {
+        self.shift_by_default
+    }
+
+    fn expected_shift_reduce_conflicts(&self) -> Option<usize> {
+        self.expected_shift_reduce
+    }
+
+    fn expected_reduce_reduce_conflicts(&self) -> Option<usize> {
+        self.expected_reduce_reduce
+    }
+
+    fn shift_reduce_precedence(
+        &self,
+        lhs: &N,
+        rhs: &Rhs<T, N, A>,
+        lookahead: Option<&T>,
+    ) -> Option<PrecedenceResolution> {
+        let (rule_level, _) = self.rule_level(lhs, rhs)?;
+        let &(token_level, assoc) = self.levels.get(lookahead?)?;
+        Some(match token_level.cmp(&rule_level) {
+            std::cmp::Ordering::Greater => PrecedenceResolution::Shift,
+            std::cmp::Ordering::Less => PrecedenceResolution::Reduce,
+            std::cmp::Ordering::Equal => match assoc {
+                Associativity::Left => PrecedenceResolution::Reduce,
+                Associativity::Right => PrecedenceResolution::Shift,
+                Associativity::NonAssoc => PrecedenceResolution::Error,
+            },
+        })
+    }
+}
+
 // -----------------------------------------------------------------------------------------------
 // ConflictWarner
 // -----------------------------------------------------------------------------------------------
@@ -121,6 +382,7 @@ impl<'a, T, N, A> ConflictWarner<'a, T, N, A> {
         state: &'b ItemSet<'a, T, N, A>,
         token: Option<&'a T>,
         rule: (&'a N, &'a Rhs<T, N, A>),
+        applied_resolution: LRConflictResolution,
     ) where
         'a: 'b,
     {
@@ -131,7 +393,7 @@ impl<'a, T, N, A> ConflictWarner<'a, T, N, A> {
                     token,
                     rule,
                 },
-                applied_resolution: LRConflictResolution::ShiftOverReduce,
+                applied_resolution,
             });
         }
     }
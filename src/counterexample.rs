@@ -0,0 +1,664 @@
+//! This module provides counterexamples for conflicts, in the style of Bison's
+//! `-Wcounterexamples`.
+//!
+//! A counterexample consists of two derivations from the start symbol, one for each of the
+//! conflicting items, in which the position of the conflict is marked with a dot and followed by
+//! the conflicting token. If both derivations produce the same sentential form, the example is
+//! *unifying* and proves that the grammar is ambiguous. Otherwise the two derivations show the
+//! competing parses, and the grammar may still be unambiguous but need more lookahead.
+//!
+//! The derivations are found by searching a graph whose nodes are the items of the LR(0) states,
+//! together with a precise lookahead token, see C. Isradisaikul and A. C. Myers, "Finding
+//! Counterexamples from Parsing Conflicts", 2015. An edge either moves the dot over a symbol,
+//! following a transition of the state machine, or enters a rule of the nonterminal after the
+//! dot. To unify the derivations, the second one is searched along the states of the first one,
+//! so that both share the symbols before the dot.
+//!
+use crate::{Grammar, Item, LR0State, LR1Conflict, Nonterminal, Rhs, Symbol, Terminal};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap};
+use std::fmt::{self, Display};
+
+/// The maximum number of nodes visited while looking for a unifying derivation.
+const UNIFICATION_LIMIT: usize = 10_000;
+
+/// A derivation from a nonterminal, possibly containing the position of a conflict.
+#[derive(Debug)]
+pub enum Derivation<'a, T: 'a, N: 'a, A: 'a> {
+    /// A symbol that is not derived any further.
+    Symbol(&'a Symbol<T, N>),
+    /// A nonterminal derived by a rule.
+    Rule {
+        /// The left-hand side of the rule.
+        lhs: &'a N,
+        /// The right-hand side of the rule.
+        rhs: &'a Rhs<T, N, A>,
+        /// The derivations of the symbols of the right-hand side. The position of the conflict
+        /// appears as an additional child.
+        children: Vec<Derivation<'a, T, N, A>>,
+    },
+    /// The position of the conflict.
+    Dot,
+}
+
+impl<'a, T, N, A> Derivation<'a, T, N, A> {
+    /// Return the sentential form derived, leaving out the position of the conflict.
+    pub fn sentential_form(&self) -> Vec<&'a Symbol<T, N>> {
+        let mut r = vec![];
+        self.collect_symbols(&mut r);
+        r
+    }
+
+    fn collect_symbols(&self, r: &mut Vec<&'a Symbol<T, N>>) {
+        match *self {
+            Derivation::Symbol(sym) => r.push(sym),
+            Derivation::Rule { ref children, .. } => {
+                for child in children.iter() {
+                    child.collect_symbols(r);
+                }
+            }
+            Derivation::Dot => {}
+        }
+    }
+}
+
+impl<'a, T: Display, N: Display, A> Derivation<'a, T, N, A> {
+    /// Write the derived sentential form with the position of the conflict.
+    pub fn fmt_example(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut first = true;
+        self.fmt_leaves(f, &mut first)
+    }
+
+    fn fmt_leaves(&self, f: &mut fmt::Formatter, first: &mut bool) -> fmt::Result {
+        match *self {
+            Derivation::Rule { ref children, .. } => {
+                for child in children.iter() {
+                    child.fmt_leaves(f, first)?;
+                }
+                return Ok(());
+            }
+            _ if *first => *first = false,
+            _ => write!(f, " ")?,
+        }
+        match *self {
+            Derivation::Symbol(sym) => write!(f, "{}", sym),
+            _ => write!(f, "•"),
+        }
+    }
+}
+
+impl<'a, T: Display, N: Display, A> Display for Derivation<'a, T, N, A> {
+    /// Write the derivation in the bracketed form of Bison, e.g. `E → [ E → [ E + E • ] + E ]`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Derivation::Symbol(sym) => write!(f, "{}", sym),
+            Derivation::Rule {
+                lhs, ref children, ..
+            } => {
+                write!(f, "{} → [", lhs)?;
+                for child in children.iter() {
+                    write!(f, " {}", child)?;
+                }
+                write!(f, " ]")
+            }
+            Derivation::Dot => write!(f, "•"),
+        }
+    }
+}
+
+/// A counterexample for a conflict.
+#[derive(Debug)]
+pub struct Counterexample<'a, T: 'a, N: 'a, A: 'a> {
+    /// A shortest sequence of symbols leading from the starting state to the conflicting state.
+    pub prefix: Vec<&'a Symbol<T, N>>,
+    /// The conflicting token, or `None` if the token is EOF.
+    pub token: Option<&'a T>,
+    /// The derivation reducing by the rule of a shift-reduce conflict, or by the first rule of a
+    /// reduce-reduce conflict.
+    pub first: Derivation<'a, T, N, A>,
+    /// The derivation shifting the token of a shift-reduce conflict, or reducing by the second rule
+    /// of a reduce-reduce conflict.
+    pub second: Derivation<'a, T, N, A>,
+    /// Whether both derivations produce the same sentential form, which proves that the grammar is
+    /// ambiguous.
+    pub unifying: bool,
+}
+
+impl<'a, T: Display, N: Display, A> Display for Counterexample<'a, T, N, A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: ",
+            if self.unifying {
+                "Example"
+            } else {
+                "First example"
+            }
+        )?;
+        self.first.fmt_example(f)?;
+        writeln!(f)?;
+        writeln!(f, "First derivation: {}", self.first)?;
+        if !self.unifying {
+            write!(f, "Second example: ")?;
+            self.second.fmt_example(f)?;
+            writeln!(f)?;
+        }
+        writeln!(f, "Second derivation: {}", self.second)
+    }
+}
+
+impl<T: Ord, N: Ord, A> Grammar<T, N, A> {
+    /// Find a counterexample for a conflict reported for this grammar.
+    ///
+    /// Returns `None` if the state of the conflict is not a state of the
+    /// [`LR0StateMachine`](../struct.LR0StateMachine.html) of the grammar.
+    pub fn counterexample<'a>(
+        &'a self,
+        conflict: &LR1Conflict<'a, T, N, A>,
+    ) -> Option<Counterexample<'a, T, N, A>> {
+        let state_machine = self.lr0_state_machine();
+        let (item_set, token, first, second) = match *conflict {
+            LR1Conflict::ReduceReduce {
+                ref state,
+                token,
+                r1,
+                r2,
+            } => (
+                state,
+                token,
+                vec![Target::reduce(r1, token)],
+                vec![Target::reduce(r2, token)],
+            ),
+            LR1Conflict::ShiftReduce {
+                ref state,
+                token,
+                rule,
+            } => {
+                let shifts = state
+                    .items
+                    .iter()
+                    .filter(|item| match (item.rhs.syms.get(item.pos), token) {
+                        (Some(Terminal(t)), Some(token)) => t == token,
+                        _ => false,
+                    })
+                    .map(|item| Target {
+                        item: item.clone(),
+                        lookahead: None,
+                    })
+                    .collect();
+                (state, token, vec![Target::reduce(rule, token)], shifts)
+            }
+        };
+        let state = state_machine
+            .states
+            .iter()
+            .position(|(iset, _)| iset == item_set)?;
+        let search = Search::new(self, &state_machine.states);
+
+        // Prefer a unifying example, trying both items of a conflict for the first derivation.
+        let mut found = None;
+        'search: for t1 in first.iter() {
+            for t2 in second.iter() {
+                match search.examples(state, t1, t2) {
+                    Some((d1, d2, true)) => {
+                        found = Some((d1, d2, true));
+                        break 'search;
+                    }
+                    Some(examples) if found.is_none() => found = Some(examples),
+                    _ => {}
+                }
+                if let Some((d2, d1, true)) = search.examples(state, t2, t1) {
+                    found = Some((d1, d2, true));
+                    break 'search;
+                }
+            }
+        }
+        let (first, second, unifying) = found?;
+        Some(Counterexample {
+            prefix: state_machine.shortest_path(state)?,
+            token,
+            first,
+            second,
+            unifying,
+        })
+    }
+}
+
+/// A conflicting item to derive, with the lookahead that has to follow a completed item.
+struct Target<'a, T: 'a, N: 'a, A: 'a> {
+    item: Item<'a, T, N, A>,
+    lookahead: Option<Option<&'a T>>,
+}
+
+impl<'a, T, N, A> Target<'a, T, N, A> {
+    fn reduce(rule: (&'a N, &'a Rhs<T, N, A>), token: Option<&'a T>) -> Self {
+        Target {
+            item: Item {
+                lhs: rule.0,
+                rhs: rule.1,
+                pos: rule.1.syms.len(),
+            },
+            lookahead: Some(token),
+        }
+    }
+}
+
+/// The derivations for two conflicting items, and whether they are unifying.
+type Examples<'a, T, N, A> = (Derivation<'a, T, N, A>, Derivation<'a, T, N, A>, bool);
+
+/// A rule and the position of the symbol in the rule deriving a terminal first, for each
+/// nonterminal and each terminal of its FIRST set.
+type FirstDerivations<'a, T, N, A> = BTreeMap<&'a N, BTreeMap<&'a T, (&'a Rhs<T, N, A>, usize)>>;
+
+/// A node of the search graph: the index of a state (or of a position in a fixed sequence of
+/// states), an item of the state and the lookahead following the item.
+type Node<'a, T, N, A> = (usize, Item<'a, T, N, A>, Option<&'a T>);
+
+/// A search for derivations in the LR(0) state machine.
+struct Search<'a, 's, T: 'a, N: 'a, A: 'a> {
+    grammar: &'a Grammar<T, N, A>,
+    states: &'s [LR0State<'a, T, N, A>],
+    /// A rule deriving the empty string for each nullable nonterminal.
+    empty: BTreeMap<&'a N, &'a Rhs<T, N, A>>,
+    /// The rules deriving the terminals of the FIRST sets.
+    first: FirstDerivations<'a, T, N, A>,
+}
+
+/// The state of the search for a second derivation producing the same sentential form as the
+/// first one.
+struct Unification<'a, 'u, T: 'a, N: 'a, A: 'a> {
+    /// The states of the first derivation before the dot.
+    path: Vec<usize>,
+    target: &'u Target<'a, T, N, A>,
+    /// The sentential form of the first derivation.
+    form: Vec<&'a Symbol<T, N>>,
+    /// The nodes of the current path.
+    nodes: Vec<Node<'a, T, N, A>>,
+    on_path: BTreeSet<Node<'a, T, N, A>>,
+    budget: usize,
+    /// The unifying derivation, or else the first derivation found.
+    derivation: Option<Derivation<'a, T, N, A>>,
+}
+
+impl<'a, 's, T: Ord, N: Ord, A> Search<'a, 's, T, N, A> {
+    fn new(grammar: &'a Grammar<T, N, A>, states: &'s [LR0State<'a, T, N, A>]) -> Self {
+        // Each entry only depends on entries that exist before it is added, so the derivations
+        // built from them are finite.
+        let mut empty = BTreeMap::new();
+        loop {
+            let mut changed = false;
+            for (lhs, rhss) in grammar.rules.iter() {
+                for rhs in rhss.iter() {
+                    if !empty.contains_key(lhs)
+                        && rhs.syms.iter().all(|sym| match *sym {
+                            Terminal(_) => false,
+                            Nonterminal(ref n) => empty.contains_key(n),
+                        })
+                    {
+                        empty.insert(lhs, rhs);
+                        changed = true;
+                    }
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+        let mut first: BTreeMap<_, BTreeMap<_, _>> = grammar
+            .rules
+            .keys()
+            .map(|lhs| (lhs, BTreeMap::new()))
+            .collect();
+        loop {
+            let mut changed = false;
+            for (lhs, rhss) in grammar.rules.iter() {
+                for rhs in rhss.iter() {
+                    for (i, sym) in rhs.syms.iter().enumerate() {
+                        let terminals: Vec<_> = match *sym {
+                            Terminal(ref t) => vec![t],
+                            Nonterminal(ref n) => first
+                                .get(n)
+                                .map(|f| f.keys().cloned().collect())
+                                .unwrap_or_default(),
+                        };
+                        let entry = first.get_mut(lhs).unwrap();
+                        for t in terminals {
+                            if !entry.contains_key(t) {
+                                entry.insert(t, (rhs, i));
+                                changed = true;
+                            }
+                        }
+                        match *sym {
+                            Nonterminal(ref n) if empty.contains_key(n) => {}
+                            _ => break,
+                        }
+                    }
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+        Search {
+            grammar,
+            states,
+            empty,
+            first,
+        }
+    }
+
+    /// Find derivations for two conflicting items in `state`.
+    ///
+    /// The first derivation is a shortest one. The second derivation shares the symbols before
+    /// the dot with the first one if possible, and is preferably one producing the same
+    /// sentential form. Returns the derivations and whether they are unifying.
+    fn examples(
+        &self,
+        state: usize,
+        t1: &Target<'a, T, N, A>,
+        t2: &Target<'a, T, N, A>,
+    ) -> Option<Examples<'a, T, N, A>> {
+        let nodes = self.shortest(state, t1)?;
+        let d1 = self.derivation(&nodes, t1);
+        let mut unification = Unification {
+            path: nodes
+                .iter()
+                .enumerate()
+                .filter(|&(i, node)| i == 0 || node.1.pos > 0)
+                .map(|(_, node)| node.0)
+                .collect(),
+            target: t2,
+            form: d1.sentential_form(),
+            nodes: vec![],
+            on_path: BTreeSet::new(),
+            budget: UNIFICATION_LIMIT,
+            derivation: None,
+        };
+        let unified = self.unify(&mut unification, self.start());
+        let d2 = match unification.derivation {
+            Some(d2) => d2,
+            None => self.derivation(&self.shortest(state, t2)?, t2),
+        };
+        Some((d1, d2, unified))
+    }
+
+    /// The node of the start item in the starting state, followed by EOF.
+    fn start(&self) -> Node<'a, T, N, A> {
+        let start = &self.grammar.start;
+        let item = Item {
+            lhs: start,
+            rhs: &self.grammar.rules[start][0],
+            pos: 0,
+        };
+        (0, item, None)
+    }
+
+    /// Return the successors of a node, each with the number of transitions on the edge.
+    ///
+    /// If `path` is given, the first component of a node is a position in `path`, and only the
+    /// transitions along `path` are followed. If the target does not require a lookahead, the
+    /// lookaheads are not tracked.
+    fn successors(
+        &self,
+        node: &Node<'a, T, N, A>,
+        path: Option<&[usize]>,
+        target: &Target<'a, T, N, A>,
+    ) -> Vec<(Node<'a, T, N, A>, usize)> {
+        let (ix, ref item, lookahead) = *node;
+        let state = path.map_or(ix, |path| path[ix]);
+        let mut r = vec![];
+        let sym = match item.rhs.syms.get(item.pos) {
+            Some(sym) => sym,
+            None => return r,
+        };
+        let next = Item {
+            lhs: item.lhs,
+            rhs: item.rhs,
+            pos: item.pos + 1,
+        };
+        let target_state = self.states[state].1[sym];
+        match path {
+            Some(path) if ix + 1 < path.len() && path[ix + 1] == target_state => {
+                r.push(((ix + 1, next, lookahead), 1));
+            }
+            Some(_) => {}
+            None => r.push(((target_state, next, lookahead), 1)),
+        }
+        if let Nonterminal(ref n) = *sym {
+            let lookaheads = if target.lookahead.is_some() {
+                self.first_of(&item.rhs.syms[item.pos + 1..], lookahead)
+            } else {
+                Some(None).into_iter().collect()
+            };
+            for new_item in self.states[state].0.items.iter() {
+                if new_item.pos == 0 && new_item.lhs == n {
+                    for &l in lookaheads.iter() {
+                        r.push(((ix, new_item.clone(), l), 0));
+                    }
+                }
+            }
+        }
+        r
+    }
+
+    /// Check whether a node is the target in the given state, or at the end of `path`.
+    fn is_target(
+        &self,
+        node: &Node<'a, T, N, A>,
+        state: usize,
+        path: Option<&[usize]>,
+        target: &Target<'a, T, N, A>,
+    ) -> bool {
+        let at_end = match path {
+            Some(path) => node.0 == path.len() - 1,
+            None => node.0 == state,
+        };
+        at_end
+            && node.1 == target.item
+            && target
+                .lookahead
+                .map_or(true, |lookahead| node.2 == lookahead)
+    }
+
+    /// Find a path to the target in `state` with the fewest transitions, and among those one
+    /// entering the fewest rules.
+    fn shortest(
+        &self,
+        state: usize,
+        target: &Target<'a, T, N, A>,
+    ) -> Option<Vec<Node<'a, T, N, A>>> {
+        let start = self.start();
+        let mut distances = BTreeMap::new();
+        let mut predecessors: BTreeMap<Node<'a, T, N, A>, Node<'a, T, N, A>> = BTreeMap::new();
+        let mut to_visit = BinaryHeap::new();
+        distances.insert(start.clone(), (0, 0));
+        to_visit.push(Reverse(((0, 0), start)));
+        while let Some(Reverse((distance, node))) = to_visit.pop() {
+            if distances[&node] < distance {
+                continue;
+            }
+            if self.is_target(&node, state, None, target) {
+                let mut nodes = vec![node];
+                while let Some(previous) = predecessors.get(nodes.last().unwrap()) {
+                    nodes.push(previous.clone());
+                }
+                nodes.reverse();
+                return Some(nodes);
+            }
+            for (next, transitions) in self.successors(&node, None, target) {
+                let d = (distance.0 + transitions, distance.1 + 1 - transitions);
+                if distances.get(&next).map_or(true, |&old| d < old) {
+                    distances.insert(next.clone(), d);
+                    predecessors.insert(next.clone(), node.clone());
+                    to_visit.push(Reverse((d, next)));
+                }
+            }
+        }
+        None
+    }
+
+    /// Enumerate the paths from `node` to the target along the states of the unification, until
+    /// one of them produces the sentential form or the budget is exhausted. Returns whether such a
+    /// path was found.
+    fn unify(
+        &self,
+        unification: &mut Unification<'a, '_, T, N, A>,
+        node: Node<'a, T, N, A>,
+    ) -> bool {
+        if unification.budget == 0 || unification.on_path.contains(&node) {
+            return false;
+        }
+        unification.budget -= 1;
+        let path = &unification.path[..];
+        let target = unification.target;
+        let successors = self.successors(&node, Some(path), target);
+        let is_target = self.is_target(&node, 0, Some(path), target);
+        unification.on_path.insert(node.clone());
+        unification.nodes.push(node);
+        let mut unified = false;
+        if is_target {
+            let derivation = self.derivation(&unification.nodes, target);
+            unified = derivation.sentential_form() == unification.form;
+            if unified || unification.derivation.is_none() {
+                unification.derivation = Some(derivation);
+            }
+        }
+        for (next, _) in successors {
+            if unified {
+                break;
+            }
+            unified = self.unify(unification, next);
+        }
+        let node = unification.nodes.pop().unwrap();
+        unification.on_path.remove(&node);
+        unified
+    }
+
+    /// Build the derivation for a path to a target.
+    ///
+    /// Each transition adds a symbol to the rule of the current item, each entered rule starts a
+    /// nested derivation. After the dot, the rules are completed such that the lookahead of the
+    /// target follows the dot.
+    fn derivation(
+        &self,
+        nodes: &[Node<'a, T, N, A>],
+        target: &Target<'a, T, N, A>,
+    ) -> Derivation<'a, T, N, A> {
+        // The rules entered so far, with their children and the lookahead following them
+        let mut frames = vec![];
+        for node in nodes.iter() {
+            let item = &node.1;
+            if item.pos == 0 {
+                frames.push((item.lhs, item.rhs, vec![], node.2));
+            } else if let Some(frame) = frames.last_mut() {
+                frame
+                    .2
+                    .push(Derivation::Symbol(&item.rhs.syms[item.pos - 1]));
+            }
+        }
+        let (lhs, rhs, mut children, mut lookahead) = frames.pop().unwrap();
+        let pos = children.len();
+        children.push(Derivation::Dot);
+        children.extend(rhs.syms[pos..].iter().map(Derivation::Symbol));
+        // The lookahead follows the dot immediately unless the target item is completed.
+        let mut placed = target.lookahead.is_none() || pos < rhs.syms.len();
+        let mut derivation = Derivation::Rule { lhs, rhs, children };
+        while let Some((lhs, rhs, mut children, parent_lookahead)) = frames.pop() {
+            let pos = children.len();
+            children.push(derivation);
+            let rest = &rhs.syms[pos + 1..];
+            match lookahead {
+                _ if placed => children.extend(rest.iter().map(Derivation::Symbol)),
+                Some(t) if self.first_of(rest, None).contains(&Some(t)) => {
+                    children.extend(self.derive_first(rest, t));
+                    placed = true;
+                }
+                _ => children.extend(rest.iter().map(|sym| self.derive_empty(sym))),
+            }
+            lookahead = parent_lookahead;
+            derivation = Derivation::Rule { lhs, rhs, children };
+        }
+        derivation
+    }
+
+    /// Compute the FIRST set of `syms` followed by `lookahead`.
+    fn first_of(
+        &self,
+        syms: &'a [Symbol<T, N>],
+        lookahead: Option<&'a T>,
+    ) -> BTreeSet<Option<&'a T>> {
+        let mut r = BTreeSet::new();
+        for sym in syms.iter() {
+            match *sym {
+                Terminal(ref t) => {
+                    r.insert(Some(t));
+                    return r;
+                }
+                Nonterminal(ref n) => {
+                    r.extend(self.first[n].keys().map(|&t| Some(t)));
+                    if !self.empty.contains_key(n) {
+                        return r;
+                    }
+                }
+            }
+        }
+        r.insert(lookahead);
+        r
+    }
+
+    /// Derive `syms` to a sentential form starting with the terminal `t`.
+    fn derive_first(&self, syms: &'a [Symbol<T, N>], t: &'a T) -> Vec<Derivation<'a, T, N, A>> {
+        let mut r = vec![];
+        let mut derived = false;
+        for sym in syms.iter() {
+            if derived {
+                r.push(Derivation::Symbol(sym));
+                continue;
+            }
+            match *sym {
+                Nonterminal(ref n) if !self.first[n].contains_key(t) => {
+                    r.push(self.derive_empty(sym));
+                }
+                _ => {
+                    r.push(self.derive_symbol_first(sym, t));
+                    derived = true;
+                }
+            }
+        }
+        r
+    }
+
+    /// Derive a symbol to a sentential form starting with the terminal `t`.
+    fn derive_symbol_first(&self, sym: &'a Symbol<T, N>, t: &'a T) -> Derivation<'a, T, N, A> {
+        match *sym {
+            Terminal(_) => Derivation::Symbol(sym),
+            Nonterminal(ref n) => {
+                let (rhs, i) = self.first[n][t];
+                let mut children: Vec<_> =
+                    rhs.syms[..i].iter().map(|s| self.derive_empty(s)).collect();
+                children.push(self.derive_symbol_first(&rhs.syms[i], t));
+                children.extend(rhs.syms[i + 1..].iter().map(Derivation::Symbol));
+                Derivation::Rule {
+                    lhs: n,
+                    rhs,
+                    children,
+                }
+            }
+        }
+    }
+
+    /// Derive a nullable symbol to the empty string.
+    fn derive_empty(&self, sym: &'a Symbol<T, N>) -> Derivation<'a, T, N, A> {
+        match *sym {
+            Terminal(_) => Derivation::Symbol(sym),
+            Nonterminal(ref n) => {
+                let rhs = self.empty[n];
+                Derivation::Rule {
+                    lhs: n,
+                    rhs,
+                    children: rhs.syms.iter().map(|s| self.derive_empty(s)).collect(),
+                }
+            }
+        }
+    }
+}
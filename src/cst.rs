@@ -0,0 +1,191 @@
+//! This module provides a concrete syntax tree, built by the parser with the
+//! [`CstBuilder`](struct.CstBuilder.html) actions.
+//!
+//! The tree is lossless: its leaves are all the tokens of the input in their order, and each
+//! interior node is labelled with the rule it was reduced by. Every node records the span it
+//! covers. The spans are computed from a function giving the span of each token, so they can be
+//! byte ranges of the source or ranges of token indices.
+//!
+use crate::parser::{Actions, SyntaxError};
+use crate::{DefaultReductionTable, LR1ParseTable, Rhs};
+use std::marker::PhantomData;
+use std::ops::Range;
+
+/// A node of a concrete syntax tree.
+#[derive(Debug)]
+pub enum Cst<'a, T: 'a, N: 'a, A: 'a, Tok> {
+    /// A token.
+    Token {
+        /// The token.
+        token: Tok,
+        /// The span of the token.
+        span: Range<usize>,
+    },
+    /// A nonterminal, derived by a rule.
+    Node {
+        /// The left-hand side of the rule.
+        lhs: &'a N,
+        /// The right-hand side of the rule.
+        rhs: &'a Rhs<T, N, A>,
+        /// The nodes of the symbols of the right-hand side.
+        children: Vec<Cst<'a, T, N, A, Tok>>,
+        /// The span covered by the children. For an empty right-hand side, this is an empty span
+        /// at the end of the preceding token.
+        span: Range<usize>,
+    },
+}
+
+impl<'a, T, N, A, Tok: Clone> Clone for Cst<'a, T, N, A, Tok> {
+    fn clone(&self) -> Self {
+        match *self {
+            Cst::Token {
+                ref token,
+                ref span,
+            } => Cst::Token {
+                token: token.clone(),
+                span: span.clone(),
+            },
+            Cst::Node {
+                lhs,
+                rhs,
+                ref children,
+                ref span,
+            } => Cst::Node {
+                lhs,
+                rhs,
+                children: children.clone(),
+                span: span.clone(),
+            },
+        }
+    }
+}
+
+impl<'a, T, N, A, Tok> Cst<'a, T, N, A, Tok> {
+    /// Return the span covered by this node.
+    pub fn span(&self) -> Range<usize> {
+        match *self {
+            Cst::Token { ref span, .. } | Cst::Node { ref span, .. } => span.clone(),
+        }
+    }
+
+    /// Return the tokens covered by this node, in their order.
+    pub fn tokens(&self) -> Vec<&Tok> {
+        let mut r = vec![];
+        let mut to_visit = vec![self];
+        while let Some(node) = to_visit.pop() {
+            match *node {
+                Cst::Token { ref token, .. } => r.push(token),
+                Cst::Node { ref children, .. } => to_visit.extend(children.iter().rev()),
+            }
+        }
+        r
+    }
+}
+
+/// The `Actions` building a concrete syntax tree.
+///
+/// `terminal` maps each token to its terminal, and `span` maps a token, given with its index in
+/// the input, to its span. Use `|index, _| index..index + 1` for spans of token indices.
+pub struct CstBuilder<Tok, L, S> {
+    terminal: L,
+    span: S,
+    /// The index of the next token.
+    index: usize,
+    /// The end of the span of the last shifted token.
+    end: usize,
+    phantom: PhantomData<fn(&Tok)>,
+}
+
+impl<Tok, L, S> CstBuilder<Tok, L, S> {
+    /// Create the actions with the given functions for terminals and spans.
+    pub fn new(terminal: L, span: S) -> Self {
+        CstBuilder {
+            terminal,
+            span,
+            index: 0,
+            end: 0,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<'a, T: 'a, N: 'a, A: 'a, Tok, L, S> Actions<'a, T, N, A> for CstBuilder<Tok, L, S>
+where
+    L: Fn(&Tok) -> T,
+    S: Fn(usize, &Tok) -> Range<usize>,
+{
+    type Token = Tok;
+    type Value = Cst<'a, T, N, A, Tok>;
+
+    fn terminal(&self, token: &Tok) -> T {
+        (self.terminal)(token)
+    }
+
+    fn shift(&mut self, token: Tok) -> Self::Value {
+        let span = (self.span)(self.index, &token);
+        self.index += 1;
+        self.end = span.end;
+        Cst::Token { token, span }
+    }
+
+    fn reduce(
+        &mut self,
+        lhs: &'a N,
+        rhs: &'a Rhs<T, N, A>,
+        children: Vec<Self::Value>,
+    ) -> Self::Value {
+        let span = match (children.first(), children.last()) {
+            (Some(first), Some(last)) => first.span().start..last.span().end,
+            _ => self.end..self.end,
+        };
+        Cst::Node {
+            lhs,
+            rhs,
+            children,
+            span,
+        }
+    }
+}
+
+impl<'a, T: Ord, N: Ord, A> LR1ParseTable<'a, T, N, A> {
+    /// Parse a sequence of tokens into a concrete syntax tree.
+    ///
+    /// `terminal` maps each token to its terminal, and `span` maps a token, given with its index
+    /// in the input, to its span. The start rule is not reduced, so the root of the tree is the node
+    /// of the symbol on its right-hand side.
+    ///
+    /// ```ignore
+    /// let tree = table.parse_cst(tokens, |token| token.kind, |_, token| token.range.clone())?;
+    /// ```
+    pub fn parse_cst<Tok, I, L, S>(
+        &self,
+        tokens: I,
+        terminal: L,
+        span: S,
+    ) -> Result<Cst<'a, T, N, A, Tok>, SyntaxError<Tok>>
+    where
+        I: IntoIterator<Item = Tok>,
+        L: Fn(&Tok) -> T,
+        S: Fn(usize, &Tok) -> Range<usize>,
+    {
+        self.parse_with(tokens, &mut CstBuilder::new(terminal, span))
+    }
+}
+
+impl<'a, T: Ord, N: Ord, A> DefaultReductionTable<'a, T, N, A> {
+    /// Parse a sequence of tokens into a concrete syntax tree, as
+    /// [`LR1ParseTable::parse_cst`](../struct.LR1ParseTable.html#method.parse_cst).
+    pub fn parse_cst<Tok, I, L, S>(
+        &self,
+        tokens: I,
+        terminal: L,
+        span: S,
+    ) -> Result<Cst<'a, T, N, A, Tok>, SyntaxError<Tok>>
+    where
+        I: IntoIterator<Item = Tok>,
+        L: Fn(&Tok) -> T,
+        S: Fn(usize, &Tok) -> Range<usize>,
+    {
+        self.parse_with(tokens, &mut CstBuilder::new(terminal, span))
+    }
+}
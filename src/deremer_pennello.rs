@@ -0,0 +1,208 @@
+//! This module provides the computation of LALR(1) lookaheads by the method of DeRemer and
+//! Pennello.
+//!
+//! Instead of building and analyzing an extended grammar, the lookaheads are computed directly on
+//! the LR(0) state machine by means of the relations *reads*, *includes* and *lookback*, see
+//! F. DeRemer and T. Pennello, "Efficient Computation of LALR(1) Look-Ahead Sets", 1982.
+//! The relations are evaluated with a traversal that collapses strongly connected components, so
+//! each set is computed only once.
+//!
+use crate::lr1::Lookaheads;
+use crate::{Item, LR0StateMachine, Nonterminal, Symbol, Terminal};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The lookaheads of each completed item, keyed by the state in which the item is completed.
+pub(crate) type ItemLookaheads<'a, T, N, A> =
+    BTreeMap<(usize, Item<'a, T, N, A>), Lookaheads<'a, T>>;
+
+impl<'a, T: Ord, N: Ord, A> LR0StateMachine<'a, T, N, A> {
+    /// Compute the LALR(1) lookaheads of the completed items.
+    pub(crate) fn deremer_pennello_lookaheads(&self) -> ItemLookaheads<'a, T, N, A> {
+        let nullable = self.nullable_nonterminals();
+        let is_nullable = |syms: &[Symbol<T, N>]| {
+            syms.iter().all(|sym| match *sym {
+                Terminal(_) => false,
+                Nonterminal(ref n) => nullable.contains(n),
+            })
+        };
+
+        // The nonterminal transitions. The start symbol never appears on a right-hand side, so
+        // the transition on it from state 0 is a pseudo transition, followed by EOF only.
+        let mut transitions = vec![(0, self.start)];
+        for (p, (_, trans)) in self.states.iter().enumerate() {
+            for &sym in trans.keys() {
+                if let Nonterminal(ref n) = *sym {
+                    transitions.push((p, n));
+                }
+            }
+        }
+        let index: BTreeMap<_, _> = transitions
+            .iter()
+            .enumerate()
+            .map(|(ix, &transition)| (transition, ix))
+            .collect();
+
+        // Direct reads and the reads relation
+        let mut read: Vec<Lookaheads<'a, T>> = Vec::with_capacity(transitions.len());
+        let mut reads = Vec::with_capacity(transitions.len());
+        for (ix, &(p, n)) in transitions.iter().enumerate() {
+            let mut direct = BTreeSet::new();
+            let mut r = vec![];
+            if ix == 0 {
+                direct.insert(None);
+            } else {
+                let target = self.goto(p, n);
+                for &sym in self.states[target].1.keys() {
+                    match *sym {
+                        Terminal(ref t) => {
+                            direct.insert(Some(t));
+                        }
+                        Nonterminal(ref c) if nullable.contains(c) => {
+                            r.push(index[&(target, c)]);
+                        }
+                        Nonterminal(_) => {}
+                    }
+                }
+            }
+            read.push(direct);
+            reads.push(r);
+        }
+        digraph(&reads, &mut read);
+
+        // The includes and lookback relations
+        let mut includes = vec![vec![]; transitions.len()];
+        let mut lookback = BTreeMap::new();
+        for (ix, &(p, n)) in transitions.iter().enumerate() {
+            for item in self.states[p].0.items.iter() {
+                if item.pos != 0 || item.lhs != n {
+                    continue;
+                }
+                let mut q = p;
+                for (i, sym) in item.rhs.syms.iter().enumerate() {
+                    if let Nonterminal(ref b) = *sym {
+                        if is_nullable(&item.rhs.syms[i + 1..]) {
+                            includes[index[&(q, b)]].push(ix);
+                        }
+                    }
+                    q = self.states[q].1[sym];
+                }
+                let completed = Item {
+                    lhs: item.lhs,
+                    rhs: item.rhs,
+                    pos: item.rhs.syms.len(),
+                };
+                lookback
+                    .entry((q, completed))
+                    .or_insert_with(Vec::new)
+                    .push(ix);
+            }
+        }
+        let mut follow = read;
+        digraph(&includes, &mut follow);
+
+        lookback
+            .into_iter()
+            .map(|(key, transitions)| {
+                let mut lookaheads = BTreeSet::new();
+                for ix in transitions {
+                    lookaheads.extend(follow[ix].iter().cloned());
+                }
+                (key, lookaheads)
+            })
+            .collect()
+    }
+
+    /// Return the state reached from `state` by the transition on the nonterminal `n`.
+    fn goto(&self, state: usize, n: &N) -> usize {
+        self.states[state]
+            .1
+            .iter()
+            .find(|&(&sym, _)| match *sym {
+                Nonterminal(ref m) => m == n,
+                Terminal(_) => false,
+            })
+            .map(|(_, &target)| target)
+            .unwrap()
+    }
+
+    /// Compute the nullable nonterminals from the rules appearing in the state machine.
+    fn nullable_nonterminals(&self) -> BTreeSet<&'a N> {
+        let rules: BTreeSet<_> = self
+            .states
+            .iter()
+            .flat_map(|(iset, _)| iset.items.iter())
+            .filter(|item| item.pos == 0)
+            .map(|item| (item.lhs, item.rhs))
+            .collect();
+        let mut nullable = BTreeSet::new();
+        loop {
+            let mut changed = false;
+            for &(lhs, rhs) in rules.iter() {
+                if !nullable.contains(lhs)
+                    && rhs.syms.iter().all(|sym| match *sym {
+                        Terminal(_) => false,
+                        Nonterminal(ref n) => nullable.contains(n),
+                    })
+                {
+                    nullable.insert(lhs);
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+        nullable
+    }
+}
+
+/// Compute the smallest sets `f` such that `f[x]` includes `f[y]` whenever `x` is related to
+/// `y`, starting from the given initial sets.
+///
+/// This is the `DIGRAPH` algorithm of DeRemer and Pennello. All members of a strongly connected
+/// component get the same set.
+fn digraph<S: Ord + Clone>(relation: &[Vec<usize>], f: &mut [BTreeSet<S>]) {
+    struct Traversal<'r, 'f, S: 'f> {
+        relation: &'r [Vec<usize>],
+        f: &'f mut [BTreeSet<S>],
+        stack: Vec<usize>,
+        depth: Vec<usize>,
+    }
+    impl<'r, 'f, S: Ord + Clone> Traversal<'r, 'f, S> {
+        fn traverse(&mut self, x: usize) {
+            self.stack.push(x);
+            let d = self.stack.len();
+            self.depth[x] = d;
+            for &y in self.relation[x].iter() {
+                if self.depth[y] == 0 {
+                    self.traverse(y);
+                }
+                self.depth[x] = self.depth[x].min(self.depth[y]);
+                if x != y {
+                    let fy = self.f[y].clone();
+                    self.f[x].extend(fy);
+                }
+            }
+            if self.depth[x] == d {
+                while let Some(top) = self.stack.pop() {
+                    self.depth[top] = usize::MAX;
+                    if top == x {
+                        break;
+                    }
+                    self.f[top] = self.f[x].clone();
+                }
+            }
+        }
+    }
+    let mut traversal = Traversal {
+        relation,
+        f,
+        stack: vec![],
+        depth: vec![0; relation.len()],
+    };
+    for x in 0..relation.len() {
+        if traversal.depth[x] == 0 {
+            traversal.traverse(x);
+        }
+    }
+}
@@ -0,0 +1,647 @@
+//! This module provides parse tables for generalized LR (GLR) parsing, which keep all the
+//! conflicting actions instead of failing.
+//!
+//! A [`GLRParseTable`](struct.GLRParseTable.html) is built by the same LALR(1) construction as
+//! [`Grammar::lalr1`](../struct.Grammar.html#method.lalr1). The `Config` still resolves the
+//! conflicts it can, e.g. by precedence, and prunes reductions with `reduce_on`. Each cell of the
+//! table then holds the actions left in conflict, so that a GLR parser can follow all of them.
+//!
+//! [`GLRParseTable::parse`](struct.GLRParseTable.html#method.parse) is such a parser, in the style
+//! of Tomita. It keeps the stacks of all the parses in a graph-structured stack, and returns a
+//! shared packed parse forest of all the parse trees. The trees of the forest can be counted,
+//! enumerated, or narrowed down to one by choosing a derivation for each ambiguous node.
+//!
+use crate::cst::Cst;
+use crate::{
+    Config, Grammar, LR1Conflict, LR1Error, LR1ParseTable, LRAction, PartialLR1ParseTable, Rhs,
+};
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::{self, Debug, Display};
+use std::ops::Range;
+
+/// A state of a GLR parse table.
+#[derive(Debug, PartialEq, Eq)]
+pub struct GLRState<'a, T: 'a, N: 'a, A: 'a> {
+    /// The actions if the lookahead is EOF.
+    pub eof: Vec<LRAction<'a, T, N, A>>,
+    /// The actions for each non-EOF lookahead.
+    ///
+    /// The actions of a cell are distinct: a shift comes first, followed by the reductions. A cell
+    /// left as an error by the configuration holds a single `LRAction::Error`.
+    pub lookahead: BTreeMap<&'a T, Vec<LRAction<'a, T, N, A>>>,
+    /// The state to jump to when shifting a nonterminal (because of a reduce rule).
+    pub goto: BTreeMap<&'a N, usize>,
+}
+
+impl<'a, T: Ord, N, A> GLRState<'a, T, N, A> {
+    /// Return the actions for the given lookahead token (`None` for EOF). There is no action for
+    /// a syntax error.
+    pub fn actions(&self, lookahead: Option<&T>) -> &[LRAction<'a, T, N, A>] {
+        let actions = match lookahead {
+            Some(t) => self
+                .lookahead
+                .get(t)
+                .map_or(&[][..], |actions| &actions[..]),
+            None => &self.eof[..],
+        };
+        match actions {
+            [LRAction::Error] => &[],
+            _ => actions,
+        }
+    }
+}
+
+/// A GLR parse table.
+#[derive(Debug, PartialEq, Eq)]
+pub struct GLRParseTable<'a, T: 'a, N: 'a, A: 'a> {
+    /// The states of the parse table.
+    pub states: Vec<GLRState<'a, T, N, A>>,
+}
+
+impl<'a, T, N, A> GLRParseTable<'a, T, N, A> {
+    /// Return the number of cells with more than one action.
+    pub fn conflicts(&self) -> usize {
+        self.states
+            .iter()
+            .flat_map(|state| state.lookahead.values().chain(Some(&state.eof)))
+            .filter(|actions| actions.len() > 1)
+            .count()
+    }
+}
+
+impl<'a, T: Ord, N, A> From<LR1ParseTable<'a, T, N, A>> for GLRParseTable<'a, T, N, A> {
+    /// Convert a deterministic parse table, with at most one action per cell.
+    fn from(table: LR1ParseTable<'a, T, N, A>) -> Self {
+        GLRParseTable {
+            states: table
+                .states
+                .into_iter()
+                .map(|state| GLRState {
+                    eof: state.eof.into_iter().collect(),
+                    lookahead: state
+                        .lookahead
+                        .into_iter()
+                        .map(|(t, action)| (t, vec![action]))
+                        .collect(),
+                    goto: state.goto,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl<'a, T: Ord, N: Ord, A> From<PartialLR1ParseTable<'a, T, N, A>> for GLRParseTable<'a, T, N, A> {
+    /// Convert a partial parse table, adding the actions of the unresolved conflicts to their
+    /// cells.
+    fn from(partial: PartialLR1ParseTable<'a, T, N, A>) -> Self {
+        let mut table = GLRParseTable::from(partial.table);
+        for ((state, token), conflicts) in partial.conflicts {
+            let state = &mut table.states[state];
+            let cell = match token {
+                Some(t) => state.lookahead.entry(t).or_default(),
+                None => &mut state.eof,
+            };
+            for conflict in conflicts {
+                match conflict {
+                    LR1Conflict::ReduceReduce { r1, r2, .. } => {
+                        add_reduction(cell, r1);
+                        add_reduction(cell, r2);
+                    }
+                    LR1Conflict::ShiftReduce { rule, .. } => add_reduction(cell, rule),
+                }
+            }
+        }
+        table
+    }
+}
+
+/// Add a reduction to the actions of a cell, unless it is already there.
+fn add_reduction<'a, T, N: Eq, A>(
+    cell: &mut Vec<LRAction<'a, T, N, A>>,
+    (lhs, rhs): (&'a N, &'a Rhs<T, N, A>),
+) {
+    let present = cell.iter().any(|action| match *action {
+        LRAction::Reduce(l, r) => l == lhs && std::ptr::eq(r, rhs),
+        _ => false,
+    });
+    if !present {
+        cell.push(LRAction::Reduce(lhs, rhs));
+    }
+}
+
+impl<T: Ord, N: Ord, A> Grammar<T, N, A> {
+    /// Create a GLR parse table out of the grammar with the LALR(1) construction.
+    ///
+    /// The conflicts that the configuration does not resolve are kept in the table, with all
+    /// their actions. Fails if the numbers of resolved conflicts differ from the numbers expected
+    /// by the configuration, as `lalr1`.
+    pub fn glr<'a>(
+        &'a self,
+        config: &'a impl Config<'a, T, N, A>,
+    ) -> Result<GLRParseTable<'a, T, N, A>, LR1Error<'a, T, N, A>> {
+        match self.lalr1_collect_conflicts(config) {
+            Ok(table) => Ok(GLRParseTable::from(table)),
+            Err(mut partial) => {
+                if partial.unexpected.is_empty() {
+                    Ok(GLRParseTable::from(partial))
+                } else {
+                    Err(partial.unexpected.remove(0))
+                }
+            }
+        }
+    }
+}
+
+/// A syntax error found by the GLR parser.
+#[derive(Debug, PartialEq, Eq)]
+pub struct SyntaxError<Tok> {
+    /// The states of the stacks in which no action was possible, in increasing order.
+    pub states: Vec<usize>,
+    /// The offending token, or `None` if the error occurred at EOF.
+    pub token: Option<Tok>,
+}
+
+impl<Tok: Debug> Display for SyntaxError<Tok> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.token {
+            Some(ref token) => write!(f, "unexpected token {:?}", token)?,
+            None => write!(f, "unexpected end of input")?,
+        }
+        write!(f, " in states {:?}", self.states)
+    }
+}
+
+/// A node of a shared packed parse forest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum NodeId {
+    /// The token with the given index in the input.
+    Token(usize),
+    /// The symbol node with the given index in the forest.
+    Symbol(usize),
+}
+
+/// A symbol node of a shared packed parse forest: a nonterminal derived from a span of the input,
+/// with all its derivations.
+#[derive(Debug)]
+pub struct SymbolNode<'a, T: 'a, N: 'a, A: 'a> {
+    /// The nonterminal.
+    pub lhs: &'a N,
+    /// The indices of the tokens derived from the nonterminal.
+    pub span: Range<usize>,
+    /// The derivations of the nonterminal, called packed nodes. There is more than one if the
+    /// span is ambiguous.
+    pub families: Vec<Family<'a, T, N, A>>,
+}
+
+/// A derivation of a symbol node by a rule.
+#[derive(Debug)]
+pub struct Family<'a, T: 'a, N: 'a, A: 'a> {
+    /// The right-hand side of the rule.
+    pub rhs: &'a Rhs<T, N, A>,
+    /// The nodes of the symbols of the right-hand side.
+    pub children: Vec<NodeId>,
+}
+
+/// A shared packed parse forest (SPPF), representing all the parse trees of an input.
+///
+/// Subtrees deriving the same nonterminal from the same span are shared, and the different
+/// derivations of a nonterminal over a span are packed in its symbol node. So the forest has a
+/// polynomial size even if the input has exponentially many parse trees. As for the other
+/// parsers, the start rule is not part of the forest: its root is the node of the symbol on the
+/// right-hand side of the start rule.
+///
+/// A grammar with a cycle `A ⇒+ A` has inputs with infinitely many parse trees, which lead to
+/// a cycle in the forest.
+#[derive(Debug)]
+pub struct Forest<'a, T: 'a, N: 'a, A: 'a, Tok> {
+    tokens: Vec<Tok>,
+    nodes: Vec<SymbolNode<'a, T, N, A>>,
+    root: NodeId,
+}
+
+/// The number of trees of a symbol node, while counting them.
+#[derive(Clone, Copy)]
+enum Count {
+    Unknown,
+    InProgress,
+    Done(Option<usize>),
+}
+
+impl<'a, T, N, A, Tok> Forest<'a, T, N, A, Tok> {
+    /// Return the root of the forest.
+    pub fn root(&self) -> NodeId {
+        self.root
+    }
+
+    /// Return the tokens of the input.
+    pub fn tokens(&self) -> &[Tok] {
+        &self.tokens
+    }
+
+    /// Return the symbol nodes of the forest.
+    pub fn nodes(&self) -> &[SymbolNode<'a, T, N, A>] {
+        &self.nodes
+    }
+
+    /// Return the indices of the tokens covered by a node.
+    pub fn span(&self, node: NodeId) -> Range<usize> {
+        match node {
+            NodeId::Token(i) => i..i + 1,
+            NodeId::Symbol(i) => self.nodes[i].span.clone(),
+        }
+    }
+
+    /// Return the number of parse trees in the forest, or `None` if there are infinitely many or
+    /// more than `usize::MAX`.
+    pub fn count(&self) -> Option<usize> {
+        self.count_node(self.root, &mut vec![Count::Unknown; self.nodes.len()])
+    }
+
+    fn count_node(&self, node: NodeId, counts: &mut [Count]) -> Option<usize> {
+        let i = match node {
+            NodeId::Token(_) => return Some(1),
+            NodeId::Symbol(i) => i,
+        };
+        match counts[i] {
+            Count::Done(count) => return count,
+            // A cycle
+            Count::InProgress => return None,
+            Count::Unknown => {}
+        }
+        counts[i] = Count::InProgress;
+        let mut total = Some(0usize);
+        for family in self.nodes[i].families.iter() {
+            let mut product = Some(1usize);
+            for &child in family.children.iter() {
+                product = product.and_then(|p| {
+                    self.count_node(child, counts)
+                        .and_then(|c| p.checked_mul(c))
+                });
+            }
+            total = total.and_then(|t| product.and_then(|p| t.checked_add(p)));
+        }
+        counts[i] = Count::Done(total);
+        total
+    }
+
+    /// Return all the parse trees in the forest, as concrete syntax trees whose spans are indices
+    /// of tokens.
+    ///
+    /// The number of trees can be exponential in the length of the input. The derivations that
+    /// would make a tree infinite are left out.
+    pub fn trees(&self) -> Vec<Cst<'a, T, N, A, &Tok>> {
+        self.all_trees(self.root, &mut vec![false; self.nodes.len()])
+    }
+
+    fn all_trees(&self, node: NodeId, on_path: &mut [bool]) -> Vec<Cst<'a, T, N, A, &Tok>> {
+        let i = match node {
+            NodeId::Token(i) => {
+                return vec![Cst::Token {
+                    token: &self.tokens[i],
+                    span: i..i + 1,
+                }]
+            }
+            NodeId::Symbol(i) => i,
+        };
+        if on_path[i] {
+            return vec![];
+        }
+        on_path[i] = true;
+        let node = &self.nodes[i];
+        let mut trees = vec![];
+        for family in node.families.iter() {
+            // The children of the trees for this family, for each combination of subtrees
+            let mut combinations = vec![vec![]];
+            for &child in family.children.iter() {
+                let subtrees = self.all_trees(child, on_path);
+                combinations = combinations
+                    .into_iter()
+                    .flat_map(|children: Vec<_>| {
+                        subtrees.iter().map(move |subtree| {
+                            let mut children = children.clone();
+                            children.push(subtree.clone());
+                            children
+                        })
+                    })
+                    .collect();
+            }
+            trees.extend(combinations.into_iter().map(|children| Cst::Node {
+                lhs: node.lhs,
+                rhs: family.rhs,
+                children,
+                span: node.span.clone(),
+            }));
+        }
+        on_path[i] = false;
+        trees
+    }
+
+    /// Return the parse tree given by choosing a derivation for each ambiguous node, as a
+    /// concrete syntax tree whose spans are indices of tokens.
+    ///
+    /// `choose` is called for each symbol node of the tree with more than one derivation, and
+    /// returns the index of the family to keep.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the chosen derivations form a cycle.
+    pub fn disambiguate<F>(&self, mut choose: F) -> Cst<'a, T, N, A, &Tok>
+    where
+        F: FnMut(&SymbolNode<'a, T, N, A>) -> usize,
+    {
+        self.tree(self.root, &mut choose, &mut vec![false; self.nodes.len()])
+    }
+
+    fn tree<F>(&self, node: NodeId, choose: &mut F, on_path: &mut [bool]) -> Cst<'a, T, N, A, &Tok>
+    where
+        F: FnMut(&SymbolNode<'a, T, N, A>) -> usize,
+    {
+        let i = match node {
+            NodeId::Token(i) => {
+                return Cst::Token {
+                    token: &self.tokens[i],
+                    span: i..i + 1,
+                }
+            }
+            NodeId::Symbol(i) => i,
+        };
+        assert!(!on_path[i], "the chosen derivations form a cycle");
+        on_path[i] = true;
+        let node = &self.nodes[i];
+        let family = if node.families.len() == 1 {
+            &node.families[0]
+        } else {
+            &node.families[choose(node)]
+        };
+        let children = family
+            .children
+            .iter()
+            .map(|&child| self.tree(child, choose, on_path))
+            .collect();
+        on_path[i] = false;
+        Cst::Node {
+            lhs: node.lhs,
+            rhs: family.rhs,
+            children,
+            span: node.span.clone(),
+        }
+    }
+}
+
+/// A node of the graph-structured stack.
+struct StackNode {
+    state: usize,
+    /// The index of the token before which the node was created.
+    level: usize,
+    /// The edges to the nodes below, labelled with the node of the forest for the symbol leading
+    /// from the node below to this one.
+    edges: Vec<(usize, NodeId)>,
+}
+
+/// An edge of the graph-structured stack, given by its source node and its index in the edges of
+/// the node.
+type Edge = (usize, usize);
+
+/// A reduction to do on a path starting from a node of the graph-structured stack.
+struct Reduction<'a, T: 'a, N: 'a, A: 'a> {
+    node: usize,
+    lhs: &'a N,
+    rhs: &'a Rhs<T, N, A>,
+    /// An edge that the path must go through, if the reduction is done again after adding the
+    /// edge.
+    via: Option<Edge>,
+}
+
+/// The state of a GLR parse.
+struct Parser<'t, 'a: 't, T: 'a, N: 'a, A: 'a, Tok> {
+    table: &'t GLRParseTable<'a, T, N, A>,
+    /// The nodes of the graph-structured stack.
+    stack: Vec<StackNode>,
+    /// The nodes of the stack at the current level, by state.
+    level: BTreeMap<usize, usize>,
+    tokens: Vec<Tok>,
+    nodes: Vec<SymbolNode<'a, T, N, A>>,
+    /// The symbol nodes ending at the current level, by nonterminal and start.
+    symbols: BTreeMap<(&'a N, usize), usize>,
+}
+
+impl<'t, 'a, T: Ord, N: Ord, A, Tok> Parser<'t, 'a, T, N, A, Tok> {
+    /// Return the states of the stacks at the current level.
+    fn states(&self) -> Vec<usize> {
+        self.level.keys().cloned().collect()
+    }
+
+    /// Add the reductions of a node of the stack on the lookahead to the queue.
+    fn push_reductions(
+        &self,
+        node: usize,
+        lookahead: Option<&T>,
+        via: Option<Edge>,
+        queue: &mut VecDeque<Reduction<'a, T, N, A>>,
+    ) {
+        for action in self.table.states[self.stack[node].state].actions(lookahead) {
+            if let LRAction::Reduce(lhs, rhs) = *action {
+                // Only the paths of a non-empty rule go through an edge
+                if via.is_none() || !rhs.syms.is_empty() {
+                    queue.push_back(Reduction {
+                        node,
+                        lhs,
+                        rhs,
+                        via,
+                    });
+                }
+            }
+        }
+    }
+
+    /// Do all the reductions at the current level on the lookahead (`None` for EOF).
+    ///
+    /// When an edge is added to an existing node, the reductions of the current level are done
+    /// again on the paths through the new edge, as in Farshi's modification of Tomita's
+    /// algorithm.
+    fn reduce(&mut self, lookahead: Option<&T>) {
+        let mut queue = VecDeque::new();
+        for &node in self.level.values() {
+            self.push_reductions(node, lookahead, None, &mut queue);
+        }
+        while let Some(reduction) = queue.pop_front() {
+            let level = self.tokens.len();
+            for (below, children) in self.paths(&reduction) {
+                let key = (reduction.lhs, self.stack[below].level);
+                let symbol = match self.symbols.get(&key) {
+                    Some(&symbol) => symbol,
+                    None => {
+                        self.nodes.push(SymbolNode {
+                            lhs: reduction.lhs,
+                            span: key.1..level,
+                            families: vec![],
+                        });
+                        self.symbols.insert(key, self.nodes.len() - 1);
+                        self.nodes.len() - 1
+                    }
+                };
+                let families = &mut self.nodes[symbol].families;
+                if !families.iter().any(|family| {
+                    std::ptr::eq(family.rhs, reduction.rhs) && family.children == children
+                }) {
+                    families.push(Family {
+                        rhs: reduction.rhs,
+                        children,
+                    });
+                }
+
+                let state = self.table.states[self.stack[below].state].goto[reduction.lhs];
+                match self.level.get(&state) {
+                    Some(&node) => {
+                        if self.stack[node].edges.iter().all(|&(n, _)| n != below) {
+                            self.stack[node].edges.push((below, NodeId::Symbol(symbol)));
+                            let edge = (node, self.stack[node].edges.len() - 1);
+                            for &node in self.level.values() {
+                                self.push_reductions(node, lookahead, Some(edge), &mut queue);
+                            }
+                        }
+                    }
+                    None => {
+                        self.stack.push(StackNode {
+                            state,
+                            level,
+                            edges: vec![(below, NodeId::Symbol(symbol))],
+                        });
+                        let node = self.stack.len() - 1;
+                        self.level.insert(state, node);
+                        self.push_reductions(node, lookahead, None, &mut queue);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Return the paths of the reduction, as the node at the end of each path with the labels of
+    /// its edges in the order of the input.
+    fn paths(&self, reduction: &Reduction<'a, T, N, A>) -> Vec<(usize, Vec<NodeId>)> {
+        let mut paths = vec![];
+        self.walk(
+            reduction.node,
+            reduction.rhs.syms.len(),
+            reduction.via,
+            &mut vec![],
+            &mut paths,
+        );
+        paths
+    }
+
+    fn walk(
+        &self,
+        node: usize,
+        len: usize,
+        via: Option<Edge>,
+        labels: &mut Vec<NodeId>,
+        paths: &mut Vec<(usize, Vec<NodeId>)>,
+    ) {
+        if len == 0 {
+            if via.is_none() {
+                paths.push((node, labels.iter().rev().cloned().collect()));
+            }
+            return;
+        }
+        for (i, &(below, label)) in self.stack[node].edges.iter().enumerate() {
+            labels.push(label);
+            let via = via.filter(|&edge| edge != (node, i));
+            self.walk(below, len - 1, via, labels, paths);
+            labels.pop();
+        }
+    }
+
+    /// Shift a token from the nodes at the current level. Returns the token if no node can shift
+    /// it.
+    fn shift(&mut self, token: Tok, terminal: &T) -> Result<(), Tok> {
+        let index = self.tokens.len();
+        let mut level = BTreeMap::new();
+        for &node in self.level.values() {
+            for action in self.table.states[self.stack[node].state].actions(Some(terminal)) {
+                if let LRAction::Shift(state) = *action {
+                    let stack = &mut self.stack;
+                    let target = *level.entry(state).or_insert_with(|| {
+                        stack.push(StackNode {
+                            state,
+                            level: index + 1,
+                            edges: vec![],
+                        });
+                        stack.len() - 1
+                    });
+                    stack[target].edges.push((node, NodeId::Token(index)));
+                }
+            }
+        }
+        if level.is_empty() {
+            return Err(token);
+        }
+        self.level = level;
+        self.symbols.clear();
+        self.tokens.push(token);
+        Ok(())
+    }
+
+    /// Return the root of the forest if a node at the current level accepts.
+    fn accept(&self) -> Option<NodeId> {
+        self.level.values().find_map(|&node| {
+            let node = &self.stack[node];
+            match self.table.states[node.state].actions(None) {
+                [LRAction::Accept] => node.edges.first().map(|&(_, label)| label),
+                _ => None,
+            }
+        })
+    }
+}
+
+impl<'a, T: Ord, N: Ord, A> GLRParseTable<'a, T, N, A> {
+    /// Parse a sequence of tokens with Tomita's algorithm, following all the actions of each cell
+    /// on a graph-structured stack.
+    ///
+    /// `terminal` maps each token to its terminal in the grammar. Returns the forest of all the
+    /// parse trees, or a syntax error if no stack can continue.
+    pub fn parse<Tok, I, L>(
+        &self,
+        tokens: I,
+        terminal: L,
+    ) -> Result<Forest<'a, T, N, A, Tok>, SyntaxError<Tok>>
+    where
+        I: IntoIterator<Item = Tok>,
+        L: Fn(&Tok) -> T,
+    {
+        let mut parser = Parser {
+            table: self,
+            stack: vec![StackNode {
+                state: 0,
+                level: 0,
+                edges: vec![],
+            }],
+            level: Some((0, 0)).into_iter().collect(),
+            tokens: vec![],
+            nodes: vec![],
+            symbols: BTreeMap::new(),
+        };
+        for token in tokens {
+            let t = terminal(&token);
+            parser.reduce(Some(&t));
+            if let Err(token) = parser.shift(token, &t) {
+                return Err(SyntaxError {
+                    states: parser.states(),
+                    token: Some(token),
+                });
+            }
+        }
+        parser.reduce(None);
+        match parser.accept() {
+            Some(root) => Ok(Forest {
+                tokens: parser.tokens,
+                nodes: parser.nodes,
+                root,
+            }),
+            None => Err(SyntaxError {
+                states: parser.states(),
+                token: None,
+            }),
+        }
+    }
+}
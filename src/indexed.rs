@@ -0,0 +1,458 @@
+//! This module provides an owned parse table, in which terminals, nonterminals, rules and states
+//! are referred to by their indices.
+//!
+//! An [`LR1ParseTable`](../struct.LR1ParseTable.html) borrows the symbols and rules of its
+//! grammar. The [`IndexedParseTable`](struct.IndexedParseTable.html) converted from it with the
+//! grammar owns everything it needs, so it can outlive the grammar, be stored next to it, or be
+//! sent to another thread.
+//!
+//! With the `serde` feature, the table can be serialized, e.g. to generate it in a build script
+//! and load it at runtime. The reductions refer to the rules by their indices, so a loaded table
+//! should be checked against its grammar with
+//! [`validate`](struct.IndexedParseTable.html#method.validate).
+//!
+use crate::parser::SyntaxError;
+use crate::{Grammar, LR1ParseTable, LRAction, Rhs, Symbol};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Display};
+
+/// An action in an indexed parse table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum IndexedAction {
+    /// Reduce by the rule with the given index.
+    Reduce(usize),
+    /// Shift, moving to the given state.
+    Shift(usize),
+    /// Accept, ending the parse.
+    Accept,
+    /// Report a syntax error, see `LRAction::Error`.
+    Error,
+}
+
+/// A rule of an indexed parse table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct IndexedRule<A> {
+    /// The index of the nonterminal on the left-hand side.
+    pub lhs: usize,
+    /// The number of symbols on the right-hand side.
+    pub len: usize,
+    /// The symbols of the right-hand side, by their indices.
+    pub syms: Vec<Symbol<usize, usize>>,
+    /// The action of the rule.
+    pub act: A,
+}
+
+/// An inconsistency between an indexed parse table and a grammar, or within the table.
+#[derive(Debug, PartialEq, Eq)]
+pub enum TableMismatch {
+    /// The terminal with the given index differs from the terminal of the grammar with the same
+    /// index, or only one of them exists.
+    Terminal(usize),
+    /// The nonterminal with the given index differs from the nonterminal of the grammar with the
+    /// same index, or only one of them exists.
+    Nonterminal(usize),
+    /// The rule with the given index differs from the rule of the grammar with the same index, by
+    /// its left-hand side, symbols or action, or only one of them exists.
+    Rule(usize),
+    /// The state with the given index has a row of the wrong length, or refers to a rule or a
+    /// state that doesn't exist.
+    State(usize),
+}
+
+impl Display for TableMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TableMismatch::Terminal(i) => write!(f, "terminal {} doesn't match the grammar", i),
+            TableMismatch::Nonterminal(i) => {
+                write!(f, "nonterminal {} doesn't match the grammar", i)
+            }
+            TableMismatch::Rule(i) => write!(f, "rule {} doesn't match the grammar", i),
+            TableMismatch::State(i) => write!(f, "state {} is inconsistent", i),
+        }
+    }
+}
+
+/// An owned parse table with dense indices.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct IndexedParseTable<T, N, A> {
+    /// The terminals, in increasing order.
+    pub terminals: Vec<T>,
+    /// The nonterminals, in increasing order.
+    pub nonterminals: Vec<N>,
+    /// The rules.
+    pub rules: Vec<IndexedRule<A>>,
+    /// The actions of each state, indexed by terminal. The last column is the action on EOF.
+    pub actions: Vec<Vec<Option<IndexedAction>>>,
+    /// The state to jump to from each state, indexed by nonterminal.
+    pub gotos: Vec<Vec<Option<usize>>>,
+}
+
+impl<T: Ord, N, A> IndexedParseTable<T, N, A> {
+    /// Return the number of states.
+    pub fn len(&self) -> usize {
+        self.actions.len()
+    }
+
+    /// Check whether the table has no states.
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+
+    /// Return the index of a terminal, if it is in the table.
+    pub fn terminal_index(&self, t: &T) -> Option<usize> {
+        self.terminals.binary_search(t).ok()
+    }
+
+    /// Return the action in a state for the terminal with the given index (`None` for EOF), if
+    /// any.
+    pub fn action(&self, state: usize, terminal: Option<usize>) -> Option<IndexedAction> {
+        self.actions[state][terminal.unwrap_or(self.terminals.len())]
+    }
+
+    /// Return the state to jump to from a state after reducing to the nonterminal with the given
+    /// index.
+    pub fn goto(&self, state: usize, nonterminal: usize) -> Option<usize> {
+        self.gotos[state][nonterminal]
+    }
+
+    /// Check that the table fits the grammar, and that its indices are consistent.
+    ///
+    /// The table must have exactly the terminals, nonterminals and rules of the grammar, but its
+    /// start symbol and start rule, in the order of [`new`](#method.new), and each rule must have
+    /// the left-hand side, symbols and action of the rule of the grammar. This is meant for a
+    /// table loaded with its grammar, as tables converted by `new` always fit their grammar.
+    pub fn validate(&self, grammar: &Grammar<T, N, A>) -> Result<(), TableMismatch>
+    where
+        N: Ord,
+        A: PartialEq,
+    {
+        let indices = GrammarIndices::new(grammar);
+        for i in 0..self.terminals.len().max(indices.terminals.len()) {
+            if self.terminals.get(i) != indices.terminals.get(i).cloned() {
+                return Err(TableMismatch::Terminal(i));
+            }
+        }
+        for i in 0..self.nonterminals.len().max(indices.nonterminals.len()) {
+            if self.nonterminals.get(i) != indices.nonterminals.get(i).cloned() {
+                return Err(TableMismatch::Nonterminal(i));
+            }
+        }
+        // Check whether a symbol of a rule of the table is the symbol of the grammar
+        let same_symbol = |(sym, g): (&Symbol<usize, usize>, &Symbol<T, N>)| match *sym {
+            Symbol::Terminal(t) => {
+                matches!(*g, Symbol::Terminal(ref g) if self.terminals.get(t) == Some(g))
+            }
+            Symbol::Nonterminal(n) => {
+                matches!(*g, Symbol::Nonterminal(ref g) if self.nonterminals.get(n) == Some(g))
+            }
+        };
+        for i in 0..self.rules.len().max(indices.rules.len()) {
+            let valid = match (self.rules.get(i), indices.rules.get(i)) {
+                (Some(rule), Some(&(lhs, rhs))) => {
+                    self.nonterminals.get(rule.lhs) == Some(lhs)
+                        && rule.len == rhs.syms.len()
+                        && rule.syms.len() == rhs.syms.len()
+                        && rule.syms.iter().zip(rhs.syms.iter()).all(same_symbol)
+                        && rule.act == rhs.act
+                }
+                _ => false,
+            };
+            if !valid {
+                return Err(TableMismatch::Rule(i));
+            }
+        }
+
+        if self.actions.is_empty() {
+            return Err(TableMismatch::State(0));
+        }
+        if self.gotos.len() != self.actions.len() {
+            return Err(TableMismatch::State(
+                self.actions.len().min(self.gotos.len()),
+            ));
+        }
+        let states = self.actions.len();
+        for (i, (actions, gotos)) in self.actions.iter().zip(self.gotos.iter()).enumerate() {
+            let valid = actions.len() == self.terminals.len() + 1
+                && gotos.len() == self.nonterminals.len()
+                && actions.iter().all(|action| match *action {
+                    Some(IndexedAction::Shift(target)) => target < states,
+                    Some(IndexedAction::Reduce(rule)) => rule < self.rules.len(),
+                    _ => true,
+                })
+                && gotos.iter().all(|goto| match *goto {
+                    Some(target) => target < states,
+                    None => true,
+                });
+            if !valid {
+                return Err(TableMismatch::State(i));
+            }
+        }
+        Ok(())
+    }
+
+    /// Parse a sequence of tokens, as
+    /// [`LR1ParseTable::parse`](../struct.LR1ParseTable.html#method.parse).
+    ///
+    /// A token whose terminal is not in the table is a syntax error.
+    pub fn parse<Tok, V, I, L, S, R>(
+        &self,
+        tokens: I,
+        terminal: L,
+        shift: S,
+        mut reduce: R,
+    ) -> Result<V, SyntaxError<Tok>>
+    where
+        I: IntoIterator<Item = Tok>,
+        L: Fn(&Tok) -> T,
+        S: FnMut(Tok) -> V,
+        R: FnMut(&A, Vec<V>) -> V,
+    {
+        parse_indexed(
+            self,
+            tokens,
+            |token| self.terminal_index(&terminal(token)),
+            shift,
+            |rule, children| reduce(&self.rules[rule].act, children),
+        )
+    }
+}
+
+/// The lookups of the parser in a parse table with dense indices.
+pub(crate) trait IndexedLookup {
+    /// Return the action in a state for the terminal with the given index (`None` for EOF), if
+    /// any.
+    fn action(&self, state: usize, terminal: Option<usize>) -> Option<IndexedAction>;
+
+    /// Return the state to jump to from a state after a reduction to the nonterminal with the
+    /// given index.
+    fn goto(&self, state: usize, nonterminal: usize) -> usize;
+
+    /// Return the index of the left-hand side of the rule with the given index, and the length of
+    /// its right-hand side.
+    fn rule(&self, rule: usize) -> (usize, usize);
+}
+
+impl<T: Ord, N, A> IndexedLookup for IndexedParseTable<T, N, A> {
+    fn action(&self, state: usize, terminal: Option<usize>) -> Option<IndexedAction> {
+        IndexedParseTable::action(self, state, terminal)
+    }
+
+    fn goto(&self, state: usize, nonterminal: usize) -> usize {
+        IndexedParseTable::goto(self, state, nonterminal).unwrap()
+    }
+
+    fn rule(&self, rule: usize) -> (usize, usize) {
+        (self.rules[rule].lhs, self.rules[rule].len)
+    }
+}
+
+/// Parse a sequence of tokens on a parse table with dense indices.
+///
+/// `terminal` maps each token to the index of its terminal, or `None` if it is not in the table,
+/// and `reduce` computes the value of a rule from its index.
+pub(crate) fn parse_indexed<P, Tok, V, I, L, S, R>(
+    table: &P,
+    tokens: I,
+    terminal: L,
+    mut shift: S,
+    mut reduce: R,
+) -> Result<V, SyntaxError<Tok>>
+where
+    P: IndexedLookup,
+    I: IntoIterator<Item = Tok>,
+    L: Fn(&Tok) -> Option<usize>,
+    S: FnMut(Tok) -> V,
+    R: FnMut(usize, Vec<V>) -> V,
+{
+    let mut tokens = tokens.into_iter();
+    let mut next = tokens.next();
+    let mut states = vec![0];
+    let mut values = vec![];
+    loop {
+        let state = *states.last().unwrap();
+        let action = match next {
+            Some(ref token) => terminal(token).and_then(|t| table.action(state, Some(t))),
+            None => table.action(state, None),
+        };
+        match action {
+            Some(IndexedAction::Shift(target)) => {
+                states.push(target);
+                values.push(shift(next.take().unwrap()));
+                next = tokens.next();
+            }
+            Some(IndexedAction::Reduce(rule)) => {
+                let (lhs, len) = table.rule(rule);
+                states.truncate(states.len() - len);
+                let children = values.split_off(values.len() - len);
+                values.push(reduce(rule, children));
+                let target = table.goto(*states.last().unwrap(), lhs);
+                states.push(target);
+            }
+            Some(IndexedAction::Accept) => return Ok(values.pop().unwrap()),
+            Some(IndexedAction::Error) | None => {
+                return Err(SyntaxError {
+                    state,
+                    token: next,
+                    stack: states,
+                })
+            }
+        }
+    }
+}
+
+impl<T: Ord + Clone, N: Ord + Clone, A: Clone> IndexedParseTable<T, N, A> {
+    /// Convert a parse table constructed from the grammar.
+    ///
+    /// The table has all the terminals of the grammar, in increasing order, its nonterminals but
+    /// the start symbol, in increasing order, and its rules but the start rule, in the order of the
+    /// grammar: by left-hand side, then in the order of the right-hand sides. The start rule is
+    /// never reduced, so it is not in the table.
+    ///
+    /// Panics if the parse table reduces by a rule that is not in the grammar.
+    pub fn new<'a>(grammar: &'a Grammar<T, N, A>, table: &LR1ParseTable<'a, T, N, A>) -> Self {
+        index_table(grammar, table, A::clone)
+    }
+}
+
+/// Convert a parse table constructed from the grammar, as
+/// [`IndexedParseTable::new`](struct.IndexedParseTable.html#method.new), with the actions of the
+/// rules converted by `act`.
+pub(crate) fn index_table<'a, T, N, A, B, F>(
+    grammar: &'a Grammar<T, N, A>,
+    table: &LR1ParseTable<'a, T, N, A>,
+    act: F,
+) -> IndexedParseTable<T, N, B>
+where
+    T: Ord + Clone,
+    N: Ord + Clone,
+    F: Fn(&A) -> B,
+{
+    let indices = GrammarIndices::new(grammar);
+    let rule_index: BTreeMap<_, _> = indices
+        .rules
+        .iter()
+        .enumerate()
+        .map(|(i, &(_, rhs))| (RuleRef(rhs), i))
+        .collect();
+    let nonterminal_index = |n: &N| indices.nonterminals.binary_search(&n).unwrap();
+
+    let action = |action: &LRAction<'a, T, N, A>| match *action {
+        LRAction::Reduce(_, rhs) => IndexedAction::Reduce(rule_index[&RuleRef(rhs)]),
+        LRAction::Shift(target) => IndexedAction::Shift(target),
+        LRAction::Accept => IndexedAction::Accept,
+        LRAction::Error => IndexedAction::Error,
+    };
+    let actions = table
+        .states
+        .iter()
+        .map(|state| {
+            indices
+                .terminals
+                .iter()
+                .map(|&t| state.lookahead.get(t).map(action))
+                .chain(Some(state.eof.as_ref().map(action)))
+                .collect()
+        })
+        .collect();
+    let gotos = table
+        .states
+        .iter()
+        .map(|state| {
+            indices
+                .nonterminals
+                .iter()
+                .map(|&n| state.goto.get(n).cloned())
+                .collect()
+        })
+        .collect();
+
+    IndexedParseTable {
+        rules: indices
+            .rules
+            .iter()
+            .map(|&(lhs, rhs)| IndexedRule {
+                lhs: nonterminal_index(lhs),
+                len: rhs.syms.len(),
+                syms: rhs
+                    .syms
+                    .iter()
+                    .map(|sym| match *sym {
+                        Symbol::Terminal(ref t) => {
+                            Symbol::Terminal(indices.terminals.binary_search(&t).unwrap())
+                        }
+                        Symbol::Nonterminal(ref n) => Symbol::Nonterminal(nonterminal_index(n)),
+                    })
+                    .collect(),
+                act: act(&rhs.act),
+            })
+            .collect(),
+        terminals: indices.terminals.into_iter().cloned().collect(),
+        nonterminals: indices.nonterminals.into_iter().cloned().collect(),
+        actions,
+        gotos,
+    }
+}
+
+/// The terminals, nonterminals and rules of a grammar, in the order of an indexed parse table.
+/// The start symbol and the start rule are left out.
+struct GrammarIndices<'g, T: 'g, N: 'g, A: 'g> {
+    terminals: Vec<&'g T>,
+    nonterminals: Vec<&'g N>,
+    rules: Vec<(&'g N, &'g Rhs<T, N, A>)>,
+}
+
+impl<'g, T: Ord, N: Ord, A> GrammarIndices<'g, T, N, A> {
+    fn new(grammar: &'g Grammar<T, N, A>) -> Self {
+        let terminals: BTreeSet<&T> = grammar
+            .rules
+            .values()
+            .flat_map(|rhss| rhss.iter().flat_map(|rhs| rhs.syms.iter()))
+            .filter_map(|sym| match *sym {
+                Symbol::Terminal(ref t) => Some(t),
+                Symbol::Nonterminal(_) => None,
+            })
+            .collect();
+        GrammarIndices {
+            terminals: terminals.into_iter().collect(),
+            nonterminals: grammar
+                .rules
+                .keys()
+                .filter(|&n| *n != grammar.start)
+                .collect(),
+            rules: grammar
+                .rules
+                .iter()
+                .filter(|&(lhs, _)| *lhs != grammar.start)
+                .flat_map(|(lhs, rhss)| rhss.iter().map(move |rhs| (lhs, rhs)))
+                .collect(),
+        }
+    }
+}
+
+/// A reference to a right-hand side, compared by address.
+struct RuleRef<'a, T: 'a, N: 'a, A: 'a>(&'a Rhs<T, N, A>);
+
+impl<'a, T, N, A> PartialEq for RuleRef<'a, T, N, A> {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self.0, other.0)
+    }
+}
+
+impl<'a, T, N, A> Eq for RuleRef<'a, T, N, A> {}
+
+impl<'a, T, N, A> PartialOrd for RuleRef<'a, T, N, A> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<'a, T, N, A> Ord for RuleRef<'a, T, N, A> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.0 as *const Rhs<T, N, A>).cmp(&(other.0 as *const Rhs<T, N, A>))
+    }
+}
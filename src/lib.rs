@@ -4,16 +4,36 @@
 //!
 //! To use this crate, you should create a [`Grammar`](struct.Grammar.html) and call
 //! [`lalr1`](struct.Grammar.html#method.lalr1). Then you can use the
-//! [`LR1ParseTable`](struct.LR1ParseTable.html) to create your own parser.
+//! [`LR1ParseTable`](struct.LR1ParseTable.html) to create your own parser, or run it with
+//! [`LR1ParseTable::parse`](struct.LR1ParseTable.html#method.parse).
 
 #![deny(missing_docs)]
 
+#[cfg(feature = "serde")]
+extern crate serde;
+
+mod builder;
+pub mod codegen;
+pub mod compressed;
 pub mod config;
+pub mod counterexample;
+pub mod cst;
+mod deremer_pennello;
+pub mod glr;
+pub mod indexed;
+mod lr1;
+pub mod parser;
+pub mod push;
+pub mod repair;
+pub mod report;
+use builder::{Abort, Collect, ConflictMode, TableBuilder};
 pub use config::Config;
-use config::ConflictWarner;
+use config::LalrLookaheads;
+use lr1::LR1Automaton;
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
-use std::cmp;
 use std::collections::{btree_map, BTreeMap, BTreeSet, VecDeque};
 use std::fmt::{self, Debug, Display};
 pub use Symbol::*;
@@ -25,6 +45,7 @@ mod tests;
 
 /// A symbol in a context-free grammar.
 #[derive(Ord, PartialOrd, Eq, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Symbol<T, N> {
     /// A terminal symbol.
     Terminal(T),
@@ -80,6 +101,7 @@ macro_rules! comparators {
 
 /// The right-hand side of a rule in a context-free grammar.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Rhs<T, N, A> {
     /// The symbols in the right-hand side of the rule.
     pub syms: Vec<Symbol<T, N>>,
@@ -113,6 +135,23 @@ impl<'a, T, N, A> Clone for Item<'a, T, N, A> {
     }
 }
 
+impl<'a, T: Display, N: Display, A> Display for Item<'a, T, N, A> {
+    /// Write the rule with a dot at the current position, e.g. `E → E + • E`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "{} →", self.lhs)?;
+        for (i, sym) in self.rhs.syms.iter().enumerate() {
+            if i == self.pos {
+                write!(f, " •")?;
+            }
+            write!(f, " {}", sym)?;
+        }
+        if self.pos == self.rhs.syms.len() {
+            write!(f, " •")?;
+        }
+        Ok(())
+    }
+}
+
 /// A set of `Item`s, forming a state in an LR(0) state machine.
 #[derive(Debug)]
 pub struct ItemSet<'a, T: 'a, N: 'a, A: 'a> {
@@ -129,8 +168,25 @@ impl<'a, T, N, A> Clone for ItemSet<'a, T, N, A> {
     }
 }
 
+impl<'a, T: Display, N: Display, A> Display for ItemSet<'a, T, N, A> {
+    /// Write the items, one per line.
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        for item in self.items.iter() {
+            writeln!(f, "{}", item)?;
+        }
+        Ok(())
+    }
+}
+
 /// A context-free grammar.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        deserialize = "T: Deserialize<'de>, N: Ord + Deserialize<'de>, A: Deserialize<'de>"
+    ))
+)]
 pub struct Grammar<T, N, A> {
     /// The rules for each nonterminal.
     pub rules: BTreeMap<N, Vec<Rhs<T, N, A>>>,
@@ -162,6 +218,13 @@ pub enum LRAction<'a, T: 'a, N: 'a, A: 'a> {
     Shift(usize),
     /// Accept, ending the parse.
     Accept,
+    /// Report a syntax error.
+    ///
+    /// This marks a cell that was deliberately left without a shift or reduce action: a conflict
+    /// resolved for a nonassociative operator, or a cell in which `Config::reduce_on` forbade
+    /// several conflicting reductions and nothing else. A missing action is an error as well, but
+    /// an `Error` action must not be replaced by a default reduction.
+    Error,
 }
 
 /// A state in an LR(1) parse table.
@@ -175,6 +238,39 @@ pub struct LR1State<'a, T: 'a, N: 'a, A: 'a> {
     pub goto: BTreeMap<&'a N, usize>,
 }
 
+impl<'a, T: Ord, N, A> LR1State<'a, T, N, A> {
+    /// Return the action for the given lookahead token (`None` for EOF), if any.
+    pub fn action(&self, lookahead: Option<&T>) -> Option<&LRAction<'a, T, N, A>> {
+        match lookahead {
+            Some(t) => self.lookahead.get(t),
+            None => self.eof.as_ref(),
+        }
+    }
+
+    /// Check whether the given lookahead token (`None` for EOF) is a syntax error, either because
+    /// there is no action or because of an explicit `LRAction::Error`.
+    pub fn is_error(&self, lookahead: Option<&T>) -> bool {
+        match self.action(lookahead) {
+            None | Some(&LRAction::Error) => true,
+            Some(_) => false,
+        }
+    }
+
+    /// Return the lookahead tokens (`None` for EOF) with an action other than an error.
+    pub fn expected(&self) -> BTreeSet<Option<&'a T>> {
+        let mut expected: BTreeSet<_> = self
+            .lookahead
+            .iter()
+            .filter(|&(_, action)| !matches!(*action, LRAction::Error))
+            .map(|(&t, _)| Some(t))
+            .collect();
+        if !self.is_error(None) {
+            expected.insert(None);
+        }
+        expected
+    }
+}
+
 /// An LR(1) parse table.
 #[derive(Debug, PartialEq, Eq)]
 pub struct LR1ParseTable<'a, T: 'a, N: 'a, A: 'a> {
@@ -182,6 +278,162 @@ pub struct LR1ParseTable<'a, T: 'a, N: 'a, A: 'a> {
     pub states: Vec<LR1State<'a, T, N, A>>,
 }
 
+impl<'a, T: Ord, N, A> LR1ParseTable<'a, T, N, A> {
+    /// Give each state with a reduction a default reduction, and remove its entries.
+    ///
+    /// The default reduction of a state is the one with the most lookaheads, the first in the
+    /// order of the lookaheads (EOF last) if there are several. The `LRAction::Error` actions are
+    /// kept. The default reductions are stored beside the table, whose states keep their shape.
+    ///
+    /// This shrinks the table, and makes the states whose only action is a reduction consistent:
+    /// the parsers reduce in them without reading the next token.
+    pub fn with_default_reductions(mut self) -> DefaultReductionTable<'a, T, N, A> {
+        let defaults = self
+            .states
+            .iter_mut()
+            .map(|state| {
+                // The reductions of the state, with their number of lookaheads
+                let mut reductions: Vec<(&'a N, &'a Rhs<T, N, A>, usize)> = vec![];
+                for action in state.lookahead.values().chain(state.eof.as_ref()) {
+                    if let LRAction::Reduce(lhs, rhs) = *action {
+                        match reductions.iter_mut().find(|r| std::ptr::eq(r.1, rhs)) {
+                            Some(r) => r.2 += 1,
+                            None => reductions.push((lhs, rhs, 1)),
+                        }
+                    }
+                }
+                let (lhs, rhs, _) = reductions.into_iter().rev().max_by_key(|r| r.2)?;
+                let is_default = |action: &LRAction<'a, T, N, A>| match *action {
+                    LRAction::Reduce(_, r) => std::ptr::eq(r, rhs),
+                    _ => false,
+                };
+                state.lookahead.retain(|_, action| !is_default(action));
+                state.eof = state.eof.take().filter(|action| !is_default(action));
+                Some(LRAction::Reduce(lhs, rhs))
+            })
+            .collect();
+        DefaultReductionTable {
+            table: self,
+            defaults,
+        }
+    }
+
+    /// Bypass the transparent unit rules, the rules `A → B` whose action is `transparent`.
+    ///
+    /// A state reached by a goto on `B` whose only action is the reduction by such a rule is
+    /// skipped: the goto leads directly to the state reached by the goto on `A`, and so on along
+    /// chains of unit rules. The language is the same, but the parsers don't reduce by the
+    /// bypassed rules, so the value of `B` becomes the value of `A`, and a concrete syntax tree has
+    /// no `A` node. The skipped states are kept, so the states are not renumbered.
+    pub fn eliminate_unit_rules<F>(&mut self, transparent: F)
+    where
+        N: Ord,
+        F: Fn(&A) -> bool,
+    {
+        // The left-hand side of the transparent unit rule each state only reduces by, if any
+        let units: Vec<Option<&'a N>> = self
+            .states
+            .iter()
+            .map(|state| {
+                let mut actions = state.lookahead.values().chain(state.eof.as_ref());
+                let (lhs, rhs) = match actions.next() {
+                    Some(&LRAction::Reduce(lhs, rhs)) => (lhs, rhs),
+                    _ => return None,
+                };
+                let unit = matches!(rhs.syms[..], [Nonterminal(_)]) && transparent(&rhs.act);
+                let only = actions.all(|action| match *action {
+                    LRAction::Reduce(_, r) => std::ptr::eq(r, rhs),
+                    _ => false,
+                });
+                if unit && only {
+                    Some(lhs)
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        for state in self.states.iter_mut() {
+            let mut bypassed = vec![];
+            for (&n, &target) in state.goto.iter() {
+                let mut bypass = target;
+                // The chain is bounded, in case of a cycle of unit rules
+                for _ in 0..units.len() {
+                    match units[bypass].and_then(|lhs| state.goto.get(lhs)) {
+                        Some(&next) => bypass = next,
+                        None => break,
+                    }
+                }
+                if bypass != target {
+                    bypassed.push((n, bypass));
+                }
+            }
+            state.goto.extend(bypassed);
+        }
+    }
+}
+
+/// An LR(1) parse table with a default reduction in each state that has a reduction, see
+/// [`with_default_reductions`](struct.LR1ParseTable.html#method.with_default_reductions).
+#[derive(Debug, PartialEq, Eq)]
+pub struct DefaultReductionTable<'a, T: 'a, N: 'a, A: 'a> {
+    /// The parse table, without the entries of the default reductions.
+    pub table: LR1ParseTable<'a, T, N, A>,
+    /// The default reduction of each state, if any.
+    pub defaults: Vec<Option<LRAction<'a, T, N, A>>>,
+}
+
+impl<'a, T: Ord, N, A> DefaultReductionTable<'a, T, N, A> {
+    /// Return the action in a state for the given lookahead token (`None` for EOF), if any,
+    /// falling back to the default reduction of the state.
+    pub fn action(&self, state: usize, lookahead: Option<&T>) -> Option<&LRAction<'a, T, N, A>> {
+        self.table.states[state]
+            .action(lookahead)
+            .or(self.defaults[state].as_ref())
+    }
+
+    /// Check whether the given lookahead token (`None` for EOF) is a syntax error in a state.
+    ///
+    /// A state with a default reduction has no errors but its `LRAction::Error` actions: an
+    /// erroneous token is reduced by the default reduction, and the error is only detected in a
+    /// later state, before the token is shifted.
+    pub fn is_error(&self, state: usize, lookahead: Option<&T>) -> bool {
+        match self.action(state, lookahead) {
+            None | Some(&LRAction::Error) => true,
+            Some(_) => false,
+        }
+    }
+
+    /// Check whether a state is consistent: its only action is the default reduction, so it
+    /// reduces without looking at the next token.
+    pub fn is_consistent(&self, state: usize) -> bool {
+        let lr1_state = &self.table.states[state];
+        self.defaults[state].is_some() && lr1_state.eof.is_none() && lr1_state.lookahead.is_empty()
+    }
+}
+
+/// A minimal LR(1) parse table, together with the LR(0) states its states were split from.
+#[derive(Debug)]
+pub struct MinimalLR1ParseTable<'a, T: 'a, N: 'a, A: 'a> {
+    /// The parse table.
+    pub table: LR1ParseTable<'a, T, N, A>,
+    /// The index of the state in the `LR0StateMachine` for each state of the parse table.
+    pub lr0_states: Vec<usize>,
+}
+
+impl<'a, T, N, A> MinimalLR1ParseTable<'a, T, N, A> {
+    /// Return the states of the `LR0StateMachine` that were split, each with the states of the
+    /// parse table it was split into.
+    pub fn split_states(&self) -> BTreeMap<usize, Vec<usize>> {
+        let mut r: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+        for (ix, &lr0_state) in self.lr0_states.iter().enumerate() {
+            r.entry(lr0_state).or_default().push(ix);
+        }
+        r.retain(|_, states| states.len() > 1);
+        r
+    }
+}
+
 /// A conflict detected while trying to construct an LR(1) parse table.
 #[derive(Debug)]
 pub enum LR1Conflict<'a, T: 'a, N: 'a, A: 'a> {
@@ -207,6 +459,53 @@ pub enum LR1Conflict<'a, T: 'a, N: 'a, A: 'a> {
     },
 }
 
+/// An error while constructing an LR(1) parse table.
+#[derive(Debug)]
+pub enum LR1Error<'a, T: 'a, N: 'a, A: 'a> {
+    /// A conflict that could not be resolved.
+    Conflict(LR1Conflict<'a, T, N, A>),
+    /// The number of resolved shift-reduce conflicts differs from
+    /// `Config::expected_shift_reduce_conflicts`.
+    UnexpectedShiftReduceConflicts {
+        /// The expected number of conflicts.
+        expected: usize,
+        /// The number of conflicts that were resolved.
+        found: usize,
+    },
+    /// The number of resolved reduce-reduce conflicts differs from
+    /// `Config::expected_reduce_reduce_conflicts`.
+    UnexpectedReduceReduceConflicts {
+        /// The expected number of conflicts.
+        expected: usize,
+        /// The number of conflicts that were resolved.
+        found: usize,
+    },
+}
+
+impl<'a, T, N, A> From<LR1Conflict<'a, T, N, A>> for LR1Error<'a, T, N, A> {
+    fn from(conflict: LR1Conflict<'a, T, N, A>) -> Self {
+        LR1Error::Conflict(conflict)
+    }
+}
+
+/// The unresolved conflicts collected while constructing a parse table, grouped by the index of the
+/// state and the token (`None` for EOF) in which they occur.
+pub type LR1Conflicts<'a, T, N, A> =
+    BTreeMap<(usize, Option<&'a T>), Vec<LR1Conflict<'a, T, N, A>>>;
+
+/// A parse table that could not be completed because of unresolved conflicts, or whose numbers of
+/// resolved conflicts differ from the numbers expected by the configuration.
+#[derive(Debug)]
+pub struct PartialLR1ParseTable<'a, T: 'a, N: 'a, A: 'a> {
+    /// The parse table. Each conflicting cell holds the first action added to it.
+    pub table: LR1ParseTable<'a, T, N, A>,
+    /// The unresolved conflicts.
+    pub conflicts: LR1Conflicts<'a, T, N, A>,
+    /// The errors for the unexpected numbers of resolved conflicts, see
+    /// `Config::expected_shift_reduce_conflicts` and `Config::expected_reduce_reduce_conflicts`.
+    pub unexpected: Vec<LR1Error<'a, T, N, A>>,
+}
+
 /// The applied resolution of an LR(1) conflict.
 #[derive(Debug)]
 pub enum LRConflictResolution {
@@ -216,6 +515,14 @@ pub enum LRConflictResolution {
     ReduceFirstRule,
     /// Resolved a reduce-reduce conflict by selecting the second rule.
     ReduceSecondRule,
+    /// Resolved a shift-reduce conflict by shifting, because the token has a higher precedence
+    /// than the rule or is right-associative.
+    ShiftByPrecedence,
+    /// Resolved a shift-reduce conflict by reducing, because the rule has a higher precedence than
+    /// the token or the token is left-associative.
+    ReduceByPrecedence,
+    /// Resolved a shift-reduce conflict by an error action, because the token is nonassociative.
+    ErrorByNonassociativity,
 }
 
 /// The resolution of an LR(1) conflict. It is reported to the user during parse table generation.
@@ -449,206 +756,158 @@ impl<T: Ord, N: Ord, A> Grammar<T, N, A> {
     pub fn lalr1<'a>(
         &'a self,
         config: &'a impl Config<'a, T, N, A>,
-    ) -> Result<LR1ParseTable<'_, T, N, A>, LR1Conflict<'_, T, N, A>> {
+    ) -> Result<LR1ParseTable<'a, T, N, A>, LR1Error<'a, T, N, A>> {
         let state_machine = self.lr0_state_machine();
-        let extended = state_machine.extended_grammar();
-        let first_sets = extended.first_sets();
-        let follow_sets = extended.follow_sets(first_sets);
-        let conflict_warner = ConflictWarner::new(config);
-        let mut r = LR1ParseTable {
-            states: state_machine
-                .states
-                .iter()
-                .map(|_| LR1State {
-                    eof: None,
-                    lookahead: BTreeMap::new(),
-                    goto: BTreeMap::new(),
-                })
-                .collect(),
-        };
+        let mut builder = TableBuilder::new(&self.start, &state_machine.states, config, Abort);
+        state_machine.add_lalr1_reductions(&mut builder, config.lalr1_lookaheads())?;
+        builder.finish()
+    }
 
-        // add shifts
-        for (i, (_, trans)) in state_machine.states.iter().enumerate() {
-            for (&sym, &target) in trans.iter() {
-                match *sym {
-                    Terminal(ref t) => {
-                        let z = r.states[i].lookahead.insert(t, LRAction::Shift(target));
-                        // can't have conflicts yet
-                        debug_assert!(z.is_none());
-                    }
-                    Nonterminal(ref n) => {
-                        let z = r.states[i].goto.insert(n, target);
-                        debug_assert!(z.is_none());
-                    }
-                }
-            }
+    /// Create an LALR(1) parse table out of the grammar, collecting all unresolved conflicts
+    /// instead of stopping at the first one.
+    ///
+    /// If there are unresolved conflicts, they are returned together with the partial parse
+    /// table. In the partial table, the first action added to a conflicting cell is kept: the shift
+    /// of a shift-reduce conflict and the first rule of a reduce-reduce conflict.
+    ///
+    /// The numbers of resolved conflicts are checked as in `lalr1`. If they differ from the
+    /// expected numbers, the partial parse table is returned as well, with the errors.
+    pub fn lalr1_collect_conflicts<'a>(
+        &'a self,
+        config: &'a impl Config<'a, T, N, A>,
+    ) -> Result<LR1ParseTable<'a, T, N, A>, PartialLR1ParseTable<'a, T, N, A>> {
+        let state_machine = self.lr0_state_machine();
+        let mut builder =
+            TableBuilder::new(&self.start, &state_machine.states, config, Collect::new());
+        match state_machine.add_lalr1_reductions(&mut builder, config.lalr1_lookaheads()) {
+            Ok(()) => {}
+            Err(never) => match never {},
         }
+        let partial = builder.finish();
+        if partial.conflicts.is_empty() && partial.unexpected.is_empty() {
+            Ok(partial.table)
+        } else {
+            Err(partial)
+        }
+    }
 
-        // add reductions
-        for ((&(start_state, lhs), rhss), (&&(s2, l2), &(ref follow, eof))) in
-            extended.rules.iter().zip(follow_sets.iter())
-        {
-            debug_assert_eq!(start_state, s2);
-            debug_assert!(lhs == l2);
+    /// Try to create a canonical LR(1) parse table out of the grammar.
+    ///
+    /// In contrast to [`lalr1`](#method.lalr1), states with the same LR(0) core are not merged
+    /// unless their lookaheads are identical. Therefore this accepts every LR(1) grammar, including
+    /// those for which merging states causes reduce-reduce conflicts in the LALR(1) table. The
+    /// price is a parse table that can have many more states.
+    ///
+    /// To use the canonical construction only when merging causes a conflict, you can fall back
+    /// to it:
+    ///
+    /// ```ignore
+    /// let parse_table = grammar.lalr1(&config).or_else(|_| grammar.lr1(&config))?;
+    /// ```
+    ///
+    /// The configuration is applied in the same way as for `lalr1`.
+    pub fn lr1<'a>(
+        &'a self,
+        config: &'a impl Config<'a, T, N, A>,
+    ) -> Result<LR1ParseTable<'a, T, N, A>, LR1Error<'a, T, N, A>> {
+        let automaton = LR1Automaton::canonical(self);
+        let mut builder = TableBuilder::new(&self.start, &automaton.states, config, Abort);
+        automaton.add_reductions(&mut builder)?;
+        builder.finish()
+    }
 
-            for &Rhs {
-                syms: _,
-                act: (end_state, rhs),
-            } in rhss.iter()
-            {
-                for &&t in follow.iter().filter(|&&&t| config.reduce_on(rhs, Some(t))) {
-                    match r.states[end_state].lookahead.entry(t) {
-                        btree_map::Entry::Vacant(v) => {
-                            v.insert(LRAction::Reduce(lhs, rhs));
-                        }
-                        btree_map::Entry::Occupied(mut v) => {
-                            match *v.get_mut() {
-                                LRAction::Reduce(l, r) if l == lhs && std::ptr::eq(r, rhs) => {
-                                    // The cells match, so there's no conflict.
-                                }
-                                LRAction::Reduce(ref mut l, ref mut r) => {
-                                    match config
-                                        .priority_of(r, Some(t))
-                                        .cmp(&config.priority_of(rhs, Some(t)))
-                                    {
-                                        cmp::Ordering::Greater => {
-                                            // `r` overrides `rhs` - do nothing.
-                                            conflict_warner.warn_reduce_reduce(
-                                                &state_machine.states[end_state].0,
-                                                Some(t),
-                                                (*l, *r),
-                                                (lhs, rhs),
-                                                LRConflictResolution::ReduceFirstRule,
-                                            );
-                                        }
-                                        cmp::Ordering::Less => {
-                                            // `rhs` overrides `r`.
-                                            *l = lhs;
-                                            *r = rhs;
-                                            conflict_warner.warn_reduce_reduce(
-                                                &state_machine.states[end_state].0,
-                                                Some(t),
-                                                (*l, *r),
-                                                (lhs, rhs),
-                                                LRConflictResolution::ReduceSecondRule,
-                                            );
-                                        }
-                                        cmp::Ordering::Equal => {
-                                            // Otherwise, we have a reduce/reduce conflict.
-                                            return Err(LR1Conflict::ReduceReduce {
-                                                state: state_machine.states[end_state].0.clone(),
-                                                token: Some(t),
-                                                r1: (*l, *r),
-                                                r2: (lhs, rhs),
-                                            });
-                                        }
-                                    }
-                                }
-                                LRAction::Shift(_) => {
-                                    // shift-reduce conflict
-                                    if config.resolve_shift_reduce_conflict_in_favor_of_shift() {
-                                        // shift wins - do nothing
-                                        conflict_warner.warn_shift_reduce(
-                                            &state_machine.states[end_state].0,
-                                            Some(t),
-                                            (lhs, rhs),
-                                        );
-                                    } else {
-                                        return Err(LR1Conflict::ShiftReduce {
-                                            state: state_machine.states[end_state].0.clone(),
-                                            token: Some(t),
-                                            rule: (lhs, rhs),
-                                        });
-                                    }
-                                }
-                                LRAction::Accept => {
-                                    unreachable!();
-                                }
-                            }
-                        }
-                    }
-                }
+    /// Try to create a minimal LR(1) parse table out of the grammar, using Pager's practical
+    /// general method.
+    ///
+    /// States with the same LR(0) core are merged as in the LALR(1) construction, unless merging
+    /// them could introduce a new reduce-reduce conflict. This avoids the conflicts that only
+    /// appear in the LALR(1) table, while the table stays close to the LALR(1) size. The returned
+    /// table maps each of its states to the state of the
+    /// [`LR0StateMachine`](struct.LR0StateMachine.html) it was split from.
+    ///
+    /// The configuration is applied in the same way as for `lalr1`.
+    pub fn minimal_lr1<'a>(
+        &'a self,
+        config: &'a impl Config<'a, T, N, A>,
+    ) -> Result<MinimalLR1ParseTable<'a, T, N, A>, LR1Error<'a, T, N, A>> {
+        let automaton = LR1Automaton::minimal(self);
+        let mut builder = TableBuilder::new(&self.start, &automaton.states, config, Abort);
+        automaton.add_reductions(&mut builder)?;
+        let lr0_states: BTreeMap<_, _> = self
+            .lr0_state_machine()
+            .states
+            .into_iter()
+            .enumerate()
+            .map(|(ix, (item_set, _))| (item_set, ix))
+            .collect();
+        Ok(MinimalLR1ParseTable {
+            table: builder.finish()?,
+            lr0_states: automaton
+                .states
+                .iter()
+                .map(|(item_set, _)| lr0_states[item_set])
+                .collect(),
+        })
+    }
 
-                if eof && config.reduce_on(rhs, None) {
-                    let state = &mut r.states[end_state];
-                    if *lhs == self.start {
-                        if state.eof.is_some() {
-                            unreachable!()
-                        }
-                        state.eof = Some(LRAction::Accept);
-                    } else {
-                        match state.eof {
-                            Some(LRAction::Reduce(l, r)) if l == lhs && std::ptr::eq(r, rhs) => {
-                                // no problem
-                            }
-                            Some(LRAction::Reduce(ref mut l, ref mut r)) => {
-                                match config
-                                    .priority_of(r, None)
-                                    .cmp(&config.priority_of(rhs, None))
-                                {
-                                    cmp::Ordering::Greater => {
-                                        // `r` overrides `rhs` - do nothing.
-                                        conflict_warner.warn_reduce_reduce(
-                                            &state_machine.states[end_state].0,
-                                            None,
-                                            (*l, *r),
-                                            (lhs, rhs),
-                                            LRConflictResolution::ReduceFirstRule,
-                                        );
-                                    }
-                                    cmp::Ordering::Less => {
-                                        // `rhs` overrides `r`.
-                                        *l = lhs;
-                                        *r = rhs;
-                                        conflict_warner.warn_reduce_reduce(
-                                            &state_machine.states[end_state].0,
-                                            None,
-                                            (*l, *r),
-                                            (lhs, rhs),
-                                            LRConflictResolution::ReduceSecondRule,
-                                        );
-                                    }
-                                    cmp::Ordering::Equal => {
-                                        // We have a reduce/reduce conflict.
-                                        return Err(LR1Conflict::ReduceReduce {
-                                            state: state_machine.states[end_state].0.clone(),
-                                            token: None,
-                                            r1: (*l, *r),
-                                            r2: (lhs, rhs),
-                                        });
-                                    }
-                                }
-                            }
-                            Some(LRAction::Shift(_)) => {
-                                // shift-reduce conflict
-                                if config.resolve_shift_reduce_conflict_in_favor_of_shift() {
-                                    // shift wins - do nothing
-                                    conflict_warner.warn_shift_reduce(
-                                        &state_machine.states[end_state].0,
-                                        None,
-                                        (lhs, rhs),
-                                    );
-                                } else {
-                                    return Err(LR1Conflict::ShiftReduce {
-                                        state: state_machine.states[end_state].0.clone(),
-                                        token: None,
-                                        rule: (lhs, rhs),
-                                    });
-                                }
-                            }
-                            Some(LRAction::Accept) => {
-                                unreachable!();
-                            }
-                            None => {
-                                state.eof = Some(LRAction::Reduce(lhs, rhs));
-                            }
-                        }
-                    }
+    /// Try to create an SLR(1) parse table out of the grammar.
+    ///
+    /// The SLR(1) construction uses the [`follow_sets`](#method.follow_sets) of the grammar as
+    /// lookaheads for the reductions in the LR(0) state machine. It accepts fewer grammars than
+    /// [`lalr1`](#method.lalr1), but the table has the same states.
+    ///
+    /// The configuration is applied in the same way as for `lalr1`.
+    pub fn slr1<'a>(
+        &'a self,
+        config: &'a impl Config<'a, T, N, A>,
+    ) -> Result<LR1ParseTable<'a, T, N, A>, LR1Error<'a, T, N, A>> {
+        let state_machine = self.lr0_state_machine();
+        let follow_sets = self.follow_sets(self.first_sets());
+        let mut builder = TableBuilder::new(&self.start, &state_machine.states, config, Abort);
+        state_machine.add_lr0_reductions(&mut builder, &follow_sets)?;
+        builder.finish()
+    }
+
+    /// Try to create an LR(0) parse table out of the grammar.
+    ///
+    /// In the LR(0) construction, a state that contains a completed item reduces by that rule on
+    /// every terminal and on EOF. Therefore any other action in such a state is a conflict. The
+    /// start rule is the exception: it only accepts on EOF.
+    ///
+    /// The configuration is applied in the same way as for `lalr1`.
+    pub fn lr0_table<'a>(
+        &'a self,
+        config: &'a impl Config<'a, T, N, A>,
+    ) -> Result<LR1ParseTable<'a, T, N, A>, LR1Error<'a, T, N, A>> {
+        let state_machine = self.lr0_state_machine();
+        let terminals = self.terminals();
+        let lookaheads = self
+            .rules
+            .keys()
+            .map(|lhs| {
+                if *lhs == self.start {
+                    (lhs, (BTreeSet::new(), true))
+                } else {
+                    (lhs, (terminals.clone(), true))
                 }
-            }
-        }
+            })
+            .collect();
+        let mut builder = TableBuilder::new(&self.start, &state_machine.states, config, Abort);
+        state_machine.add_lr0_reductions(&mut builder, &lookaheads)?;
+        builder.finish()
+    }
 
-        Ok(r)
+    /// Collect the terminals used in the rules of the grammar.
+    fn terminals(&self) -> BTreeSet<&T> {
+        self.rules
+            .values()
+            .flat_map(|rhss| rhss.iter())
+            .flat_map(|rhs| rhs.syms.iter())
+            .filter_map(|sym| match *sym {
+                Terminal(ref t) => Some(t),
+                Nonterminal(_) => None,
+            })
+            .collect()
     }
 }
 
@@ -699,6 +958,104 @@ impl<'a, T: Ord, N: Ord, A> LR0StateMachine<'a, T, N, A> {
             start: (0, self.start),
         }
     }
+
+    /// Return a shortest sequence of symbols leading from the starting state to the given state,
+    /// or `None` if the state cannot be reached.
+    pub fn shortest_path(&self, state: usize) -> Option<Vec<&'a Symbol<T, N>>> {
+        let mut predecessors = vec![None; self.states.len()];
+        let mut to_visit = VecDeque::new();
+        let mut visited = vec![false; self.states.len()];
+        visited[0] = true;
+        to_visit.push_back(0);
+        while let Some(ix) = to_visit.pop_front() {
+            if ix == state {
+                let mut path = vec![];
+                let mut current = ix;
+                while let Some((previous, sym)) = predecessors[current] {
+                    path.push(sym);
+                    current = previous;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for (&sym, &target) in self.states[ix].1.iter() {
+                if !visited[target] {
+                    visited[target] = true;
+                    predecessors[target] = Some((ix, sym));
+                    to_visit.push_back(target);
+                }
+            }
+        }
+        None
+    }
+
+    /// Add the reductions of the LALR(1) construction to the table, computing the lookaheads with
+    /// the given algorithm.
+    fn add_lalr1_reductions<M: ConflictMode<'a, T, N, A>>(
+        &self,
+        builder: &mut TableBuilder<'a, '_, T, N, A, M>,
+        algorithm: LalrLookaheads,
+    ) -> Result<(), M::Error> {
+        if algorithm == LalrLookaheads::DeRemerPennello {
+            for ((end_state, item), lookaheads) in self.deremer_pennello_lookaheads() {
+                for &t in lookaheads.iter().flatten() {
+                    builder.reduce(end_state, Some(t), item.lhs, item.rhs)?;
+                }
+                if lookaheads.contains(&None) {
+                    builder.reduce(end_state, None, item.lhs, item.rhs)?;
+                }
+            }
+            return Ok(());
+        }
+        // Use the FOLLOW sets of the extended grammar as lookaheads.
+        let extended = self.extended_grammar();
+        let first_sets = extended.first_sets();
+        let follow_sets = extended.follow_sets(first_sets);
+        for ((&(start_state, lhs), rhss), (&&(s2, l2), &(ref follow, eof))) in
+            extended.rules.iter().zip(follow_sets.iter())
+        {
+            debug_assert_eq!(start_state, s2);
+            debug_assert!(lhs == l2);
+
+            for &Rhs {
+                syms: _,
+                act: (end_state, rhs),
+            } in rhss.iter()
+            {
+                for &&t in follow.iter() {
+                    builder.reduce(end_state, Some(t), lhs, rhs)?;
+                }
+                if eof {
+                    builder.reduce(end_state, None, lhs, rhs)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Add a reduction for every completed item of the state machine, on the lookaheads given for
+    /// the left-hand side of its rule as (terminals, whether EOF is included).
+    fn add_lr0_reductions<M: ConflictMode<'a, T, N, A>>(
+        &self,
+        builder: &mut TableBuilder<'a, '_, T, N, A, M>,
+        lookaheads: &BTreeMap<&'a N, (BTreeSet<&'a T>, bool)>,
+    ) -> Result<(), M::Error> {
+        for (ix, (iset, _)) in self.states.iter().enumerate() {
+            for item in iset.items.iter() {
+                if item.pos < item.rhs.syms.len() {
+                    continue;
+                }
+                let &(ref follow, eof) = lookaheads.get(item.lhs).unwrap();
+                for &t in follow.iter() {
+                    builder.reduce(ix, Some(t), item.lhs, item.rhs)?;
+                }
+                if eof {
+                    builder.reduce(ix, None, item.lhs, item.rhs)?;
+                }
+            }
+        }
+        Ok(())
+    }
 }
 
 impl<'a, T: Debug, N: Debug, A> LR0StateMachine<'a, T, N, A> {
@@ -0,0 +1,290 @@
+//! This module provides the construction of LR(1) automata with explicit lookaheads.
+//!
+//! The states of the automaton carry their kernel items together with the lookahead tokens of
+//! each item. In contrast to the LALR(1) construction, states with the same LR(0) core are not
+//! merged unconditionally. The canonical LR(1) automaton only merges states with identical
+//! lookaheads. The minimal LR(1) automaton uses Pager's weak compatibility test to merge states
+//! whenever this cannot introduce a new reduce-reduce conflict.
+//!
+use crate::builder::{ConflictMode, TableBuilder};
+use crate::{Grammar, Item, ItemSet, LR0State, Nonterminal, Symbol, Terminal};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+/// A set of lookahead tokens, `None` standing for EOF.
+pub(crate) type Lookaheads<'a, T> = BTreeSet<Option<&'a T>>;
+
+/// Items together with their lookahead tokens.
+pub(crate) type LR1Items<'a, T, N, A> = BTreeMap<Item<'a, T, N, A>, Lookaheads<'a, T>>;
+
+type FirstSets<'a, T, N> = BTreeMap<&'a N, (BTreeSet<&'a T>, bool)>;
+
+/// An LR(1) automaton.
+pub(crate) struct LR1Automaton<'a, T: 'a, N: 'a, A: 'a> {
+    /// The LR(0) core and the transitions of each state.
+    pub states: Vec<LR0State<'a, T, N, A>>,
+    /// The kernel items of each state with their lookaheads.
+    pub kernels: Vec<LR1Items<'a, T, N, A>>,
+    first_sets: FirstSets<'a, T, N>,
+    grammar: &'a Grammar<T, N, A>,
+}
+
+impl<'a, T: Ord, N: Ord, A> LR1Automaton<'a, T, N, A> {
+    /// Create the canonical LR(1) automaton for a grammar.
+    pub fn canonical(grammar: &'a Grammar<T, N, A>) -> Self {
+        Self::build(grammar, false)
+    }
+
+    /// Create the minimal LR(1) automaton for a grammar, following Pager's practical general
+    /// method.
+    ///
+    /// A new state is merged into an existing state with the same core if the states are weakly
+    /// compatible. Lookaheads added by a merge are propagated to the successors of the merged
+    /// state.
+    pub fn minimal(grammar: &'a Grammar<T, N, A>) -> Self {
+        Self::build(grammar, true)
+    }
+
+    fn build(grammar: &'a Grammar<T, N, A>, merge: bool) -> Self {
+        let mut automaton = LR1Automaton {
+            states: vec![],
+            kernels: vec![],
+            first_sets: grammar.first_sets(),
+            grammar,
+        };
+        // the states with the same kernel core
+        let mut cores: BTreeMap<BTreeSet<Item<'a, T, N, A>>, Vec<usize>> = BTreeMap::new();
+        let mut start = BTreeMap::new();
+        start.insert(
+            Item {
+                lhs: &grammar.start,
+                rhs: &grammar.rules.get(&grammar.start).unwrap()[0],
+                pos: 0,
+            },
+            Some(None).into_iter().collect(),
+        );
+        let mut to_process: VecDeque<_> = Some(automaton.add_state(&mut cores, start))
+            .into_iter()
+            .collect();
+        while let Some(ix) = to_process.pop_front() {
+            let closure = automaton.closure(&automaton.kernels[ix]);
+            for (sym, kernel) in successors(&closure) {
+                let candidates = cores
+                    .get(&kernel.keys().cloned().collect())
+                    .cloned()
+                    .unwrap_or_default();
+                let target = if let Some(&c) = candidates.iter().find(|&&c| {
+                    if merge {
+                        includes(&automaton.kernels[c], &kernel)
+                    } else {
+                        automaton.kernels[c] == kernel
+                    }
+                }) {
+                    c
+                } else if let Some(&c) = candidates
+                    .iter()
+                    .find(|&&c| merge && weakly_compatible(&automaton.kernels[c], &kernel))
+                {
+                    for (item, lookaheads) in kernel.into_iter() {
+                        automaton.kernels[c]
+                            .get_mut(&item)
+                            .unwrap()
+                            .extend(lookaheads);
+                    }
+                    if !to_process.contains(&c) {
+                        to_process.push_back(c);
+                    }
+                    c
+                } else {
+                    let c = automaton.add_state(&mut cores, kernel);
+                    to_process.push_back(c);
+                    c
+                };
+                automaton.states[ix].1.insert(sym, target);
+            }
+        }
+        if merge {
+            automaton.remove_unreachable_states();
+        }
+        automaton
+    }
+
+    /// Add a new state for the given kernel.
+    fn add_state(
+        &mut self,
+        cores: &mut BTreeMap<BTreeSet<Item<'a, T, N, A>>, Vec<usize>>,
+        kernel: LR1Items<'a, T, N, A>,
+    ) -> usize {
+        let ix = self.states.len();
+        let core = self.closure(&kernel).into_keys().collect();
+        self.states.push((ItemSet { items: core }, BTreeMap::new()));
+        cores
+            .entry(kernel.keys().cloned().collect())
+            .or_default()
+            .push(ix);
+        self.kernels.push(kernel);
+        ix
+    }
+
+    /// Remove the states that are no longer reachable from the starting state.
+    ///
+    /// A state can become unreachable if propagating lookaheads after a merge redirects the
+    /// transitions leading to it.
+    fn remove_unreachable_states(&mut self) {
+        let mut reachable = vec![false; self.states.len()];
+        let mut to_visit = vec![0];
+        reachable[0] = true;
+        while let Some(ix) = to_visit.pop() {
+            for &target in self.states[ix].1.values() {
+                if !reachable[target] {
+                    reachable[target] = true;
+                    to_visit.push(target);
+                }
+            }
+        }
+        let mut new_index = vec![0; self.states.len()];
+        let mut count = 0;
+        for (ix, &r) in reachable.iter().enumerate() {
+            new_index[ix] = count;
+            if r {
+                count += 1;
+            }
+        }
+        if count == self.states.len() {
+            return;
+        }
+        let states = std::mem::take(&mut self.states);
+        let kernels = std::mem::take(&mut self.kernels);
+        for ((mut state, kernel), r) in states.into_iter().zip(kernels).zip(reachable) {
+            if r {
+                for target in state.1.values_mut() {
+                    *target = new_index[*target];
+                }
+                self.states.push(state);
+                self.kernels.push(kernel);
+            }
+        }
+    }
+
+    /// Compute the closure of kernel items, propagating the lookaheads to the added items.
+    pub fn closure(&self, kernel: &LR1Items<'a, T, N, A>) -> LR1Items<'a, T, N, A> {
+        let mut closure = kernel.clone();
+        let mut to_add: VecDeque<_> = kernel.keys().cloned().collect();
+        while let Some(item) = to_add.pop_front() {
+            if let Some(Nonterminal(ref n)) = item.rhs.syms.get(item.pos) {
+                let lookaheads = self.first(&item.rhs.syms[item.pos + 1..], &closure[&item]);
+                if let Some(rules) = self.grammar.rules.get(n) {
+                    for rhs in rules.iter() {
+                        let new_item = Item {
+                            lhs: n,
+                            rhs,
+                            pos: 0,
+                        };
+                        let entry = closure.entry(new_item.clone()).or_default();
+                        let len = entry.len();
+                        entry.extend(lookaheads.iter().cloned());
+                        if entry.len() > len && !to_add.contains(&new_item) {
+                            to_add.push_back(new_item);
+                        }
+                    }
+                }
+            }
+        }
+        closure
+    }
+
+    /// Compute the FIRST set of `syms` followed by one of `follow`.
+    fn first(&self, syms: &'a [Symbol<T, N>], follow: &Lookaheads<'a, T>) -> Lookaheads<'a, T> {
+        let mut r = BTreeSet::new();
+        for sym in syms.iter() {
+            match *sym {
+                Terminal(ref t) => {
+                    r.insert(Some(t));
+                    return r;
+                }
+                Nonterminal(ref n) => {
+                    let &(ref first, nullable) = self.first_sets.get(n).unwrap();
+                    r.extend(first.iter().map(|&t| Some(t)));
+                    if !nullable {
+                        return r;
+                    }
+                }
+            }
+        }
+        r.extend(follow.iter().cloned());
+        r
+    }
+
+    /// Add the reductions of all states to the table.
+    pub fn add_reductions<M: ConflictMode<'a, T, N, A>>(
+        &self,
+        builder: &mut TableBuilder<'a, '_, T, N, A, M>,
+    ) -> Result<(), M::Error> {
+        for (ix, kernel) in self.kernels.iter().enumerate() {
+            for (item, lookaheads) in self.closure(kernel).iter() {
+                if item.pos < item.rhs.syms.len() {
+                    continue;
+                }
+                for &t in lookaheads.iter().flatten() {
+                    builder.reduce(ix, Some(t), item.lhs, item.rhs)?;
+                }
+                if lookaheads.contains(&None) {
+                    builder.reduce(ix, None, item.lhs, item.rhs)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Compute the kernels of the successor states, advancing the items over each symbol.
+fn successors<'a, T: Ord, N: Ord, A>(
+    closure: &LR1Items<'a, T, N, A>,
+) -> BTreeMap<&'a Symbol<T, N>, LR1Items<'a, T, N, A>> {
+    let mut r: BTreeMap<_, LR1Items<'a, T, N, A>> = BTreeMap::new();
+    for (item, lookaheads) in closure.iter() {
+        if let Some(sym) = item.rhs.syms.get(item.pos) {
+            let next = Item {
+                lhs: item.lhs,
+                rhs: item.rhs,
+                pos: item.pos + 1,
+            };
+            r.entry(sym)
+                .or_default()
+                .entry(next)
+                .or_default()
+                .extend(lookaheads.iter().cloned());
+        }
+    }
+    r
+}
+
+/// Check whether every lookahead of `kernel` is already contained in `existing`.
+fn includes<'a, T: Ord, N: Ord, A>(
+    existing: &LR1Items<'a, T, N, A>,
+    kernel: &LR1Items<'a, T, N, A>,
+) -> bool {
+    kernel
+        .iter()
+        .all(|(item, lookaheads)| lookaheads.is_subset(&existing[item]))
+}
+
+/// Check Pager's weak compatibility of two kernels with the same core.
+///
+/// Merging the kernels can only introduce a new reduce-reduce conflict between two items if the
+/// items share a lookahead after the merge, but not in either of the kernels.
+fn weakly_compatible<'a, T: Ord, N: Ord, A>(
+    k1: &LR1Items<'a, T, N, A>,
+    k2: &LR1Items<'a, T, N, A>,
+) -> bool {
+    let l1: Vec<_> = k1.values().collect();
+    let l2: Vec<_> = k2.values().collect();
+    for i in 0..l1.len() {
+        for j in i + 1..l1.len() {
+            let new_overlap = !l1[i].is_disjoint(l2[j]) || !l2[i].is_disjoint(l1[j]);
+            if new_overlap && l1[i].is_disjoint(l1[j]) && l2[i].is_disjoint(l2[j]) {
+                return false;
+            }
+        }
+    }
+    true
+}
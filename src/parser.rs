@@ -0,0 +1,595 @@
+//! This module provides a table-driven LR parser running on an
+//! [`LR1ParseTable`](../struct.LR1ParseTable.html) or a
+//! [`DefaultReductionTable`](../struct.DefaultReductionTable.html).
+//!
+//! The parser keeps a stack of states and a parallel stack of values. A shift pushes the value of
+//! the token, a reduction replaces the values of the right-hand side by the value computed for the
+//! rule, and the parse ends when the table accepts. The values are computed by an implementation
+//! of [`Actions`](trait.Actions.html), or by callbacks.
+//!
+//! The start rule `start → N` is never reduced, since the table accepts instead, so its action is
+//! never invoked: the parse returns the value of `N`.
+//!
+use crate::{DefaultReductionTable, LR1ParseTable, LRAction, Rhs};
+use std::collections::BTreeSet;
+use std::fmt::{self, Debug, Display};
+use std::marker::PhantomData;
+
+/// A syntax error found by the parser.
+#[derive(Debug, PartialEq, Eq)]
+pub struct SyntaxError<Tok> {
+    /// The state in which no action was possible.
+    pub state: usize,
+    /// The offending token, or `None` if the error occurred at EOF.
+    pub token: Option<Tok>,
+    /// The stack of states at the error, ending with `state`, for
+    /// [`expected_on_stack`](../struct.LR1ParseTable.html#method.expected_on_stack).
+    pub(crate) stack: Vec<usize>,
+}
+
+impl<Tok: Debug> Display for SyntaxError<Tok> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.token {
+            Some(ref token) => write!(f, "unexpected token {:?}", token)?,
+            None => write!(f, "unexpected end of input")?,
+        }
+        write!(f, " in state {}", self.state)
+    }
+}
+
+/// The semantic actions of a parse, computing a value for each shifted token and each reduction.
+///
+/// The parser keeps the values in a stack parallel to the stack of states, so the values can be
+/// of any type, e.g. an enum with a variant for tokens and a variant for each kind of AST node.
+/// The action `A` attached to a rule is available as `rhs.act`.
+pub trait Actions<'a, T, N, A> {
+    /// The type of the tokens.
+    type Token;
+    /// The type of the values.
+    type Value;
+
+    /// Return the terminal of a token.
+    fn terminal(&self, token: &Self::Token) -> T;
+
+    /// Compute the value of a shifted token.
+    fn shift(&mut self, token: Self::Token) -> Self::Value;
+
+    /// Compute the value of a reduction by the rule `lhs -> rhs`, from the values of the symbols
+    /// of the right-hand side. It is never called for the start rule.
+    fn reduce(
+        &mut self,
+        lhs: &'a N,
+        rhs: &'a Rhs<T, N, A>,
+        children: Vec<Self::Value>,
+    ) -> Self::Value;
+}
+
+/// The `Actions` of a parse that recovers from syntax errors with a designated `error` terminal,
+/// see [`parse_with_recovery`](../struct.LR1ParseTable.html#method.parse_with_recovery).
+///
+/// The `error` terminal is an ordinary terminal of the grammar that the tokens never map to. Rules
+/// mention it like any other terminal, e.g. `Stmt → error ;`, and the parse table constructions
+/// add shifts for it.
+pub trait Recovery<'a, T, N, A>: Actions<'a, T, N, A> {
+    /// Return the `error` terminal.
+    fn error_terminal(&self) -> T;
+
+    /// Compute the value of a shifted `error` terminal from the values popped from the stack to
+    /// reach a state that can shift it, in the order of the input.
+    fn error_value(&mut self, popped: Vec<Self::Value>) -> Self::Value;
+
+    /// Called when a syntax error is found in `state` on the given token (`None` for EOF). Errors
+    /// found within three tokens after the previous one are not reported.
+    fn on_syntax_error(&mut self, _state: usize, _token: Option<&Self::Token>) {}
+
+    /// Called when a token is discarded after an error.
+    fn on_discard(&mut self, _token: Self::Token) {}
+}
+
+/// The `Actions` given by callbacks.
+struct Callbacks<Tok, V, L, S, R> {
+    terminal: L,
+    shift: S,
+    reduce: R,
+    phantom: PhantomData<fn(Tok) -> V>,
+}
+
+impl<'a, T, N, A, Tok, V, L, S, R> Actions<'a, T, N, A> for Callbacks<Tok, V, L, S, R>
+where
+    L: Fn(&Tok) -> T,
+    S: FnMut(Tok) -> V,
+    R: FnMut(&A, Vec<V>) -> V,
+{
+    type Token = Tok;
+    type Value = V;
+
+    fn terminal(&self, token: &Tok) -> T {
+        (self.terminal)(token)
+    }
+
+    fn shift(&mut self, token: Tok) -> V {
+        (self.shift)(token)
+    }
+
+    fn reduce(&mut self, _lhs: &'a N, rhs: &'a Rhs<T, N, A>, children: Vec<V>) -> V {
+        (self.reduce)(&rhs.act, children)
+    }
+}
+
+impl<'a, T: Ord, N: Ord, A> LR1ParseTable<'a, T, N, A> {
+    /// Parse a sequence of tokens.
+    ///
+    /// `terminal` maps each token to its terminal in the grammar. `shift` turns a shifted token
+    /// into a value, and `reduce` computes the value of a rule from its action and the values of
+    /// the symbols of its right-hand side. Returns the value of the symbol of the start rule, whose
+    /// action is never invoked, or the first syntax error.
+    ///
+    /// ```ignore
+    /// let value = table.parse(
+    ///     tokens,
+    ///     |token| token.kind,
+    ///     |token| Value::from(token),
+    ///     |act, children| act.build(children),
+    /// )?;
+    /// ```
+    pub fn parse<Tok, V, I, L, S, R>(
+        &self,
+        tokens: I,
+        terminal: L,
+        shift: S,
+        reduce: R,
+    ) -> Result<V, SyntaxError<Tok>>
+    where
+        I: IntoIterator<Item = Tok>,
+        L: Fn(&Tok) -> T,
+        S: FnMut(Tok) -> V,
+        R: FnMut(&A, Vec<V>) -> V,
+    {
+        self.parse_with(
+            tokens,
+            &mut Callbacks {
+                terminal,
+                shift,
+                reduce,
+                phantom: PhantomData,
+            },
+        )
+    }
+
+    /// Parse a sequence of tokens, computing the values with the given `Actions`.
+    ///
+    /// Returns the value of the symbol of the start rule, or the first syntax error.
+    pub fn parse_with<I, Ac>(
+        &self,
+        tokens: I,
+        actions: &mut Ac,
+    ) -> Result<Ac::Value, SyntaxError<Ac::Token>>
+    where
+        I: IntoIterator<Item = Ac::Token>,
+        Ac: Actions<'a, T, N, A>,
+    {
+        parse_on(self, tokens, actions)
+    }
+
+    /// Parse a sequence of tokens, computing the values with the given `Actions` and recovering
+    /// from syntax errors in the panic mode of Yacc.
+    ///
+    /// At a syntax error, the reductions that don't depend on the offending token are applied
+    /// first, so a phrase completed before the error, like a statement followed by a stray `;`,
+    /// is kept. Then states are popped from the stack until one can shift the `error` terminal,
+    /// which is then shifted. After that, tokens are discarded until parsing can
+    /// continue. Errors are only reported again after three tokens have been shifted, so one
+    /// mistake doesn't cause a cascade of reports.
+    ///
+    /// Returns the value of the symbol of the start rule, or the last syntax error if the parser
+    /// could not recover: no state on the stack can shift `error`, or the input ended while
+    /// discarding.
+    pub fn parse_with_recovery<I, Ac>(
+        &self,
+        tokens: I,
+        actions: &mut Ac,
+    ) -> Result<Ac::Value, SyntaxError<Ac::Token>>
+    where
+        I: IntoIterator<Item = Ac::Token>,
+        Ac: Recovery<'a, T, N, A>,
+    {
+        recover_on(self, tokens, actions)
+    }
+
+    /// Return the lookahead tokens (`None` for EOF) accepted on the stack of states at a syntax
+    /// error, i.e. shifted or accepted after the reductions they cause.
+    ///
+    /// The lookahead of an LALR(1) state may contain tokens that are a syntax error after the
+    /// reductions, since the state merges the lookaheads of several contexts. Unlike
+    /// [`LR1State::expected`](../struct.LR1State.html#method.expected) on the state of the
+    /// error, this follows the reductions to keep only the tokens that are really accepted.
+    pub fn expected_on_stack<Tok>(&self, error: &SyntaxError<Tok>) -> BTreeSet<Option<&'a T>> {
+        self.expected_on(&error.stack)
+    }
+}
+
+impl<'a, T: Ord, N: Ord, A> DefaultReductionTable<'a, T, N, A> {
+    /// Parse a sequence of tokens, as
+    /// [`LR1ParseTable::parse`](../struct.LR1ParseTable.html#method.parse).
+    ///
+    /// A consistent state reduces without reading the next token. A syntax error may be found
+    /// after a few default reductions, but always before the erroneous token is shifted.
+    pub fn parse<Tok, V, I, L, S, R>(
+        &self,
+        tokens: I,
+        terminal: L,
+        shift: S,
+        reduce: R,
+    ) -> Result<V, SyntaxError<Tok>>
+    where
+        I: IntoIterator<Item = Tok>,
+        L: Fn(&Tok) -> T,
+        S: FnMut(Tok) -> V,
+        R: FnMut(&A, Vec<V>) -> V,
+    {
+        self.parse_with(
+            tokens,
+            &mut Callbacks {
+                terminal,
+                shift,
+                reduce,
+                phantom: PhantomData,
+            },
+        )
+    }
+
+    /// Parse a sequence of tokens, computing the values with the given `Actions`, as
+    /// [`parse`](#method.parse).
+    pub fn parse_with<I, Ac>(
+        &self,
+        tokens: I,
+        actions: &mut Ac,
+    ) -> Result<Ac::Value, SyntaxError<Ac::Token>>
+    where
+        I: IntoIterator<Item = Ac::Token>,
+        Ac: Actions<'a, T, N, A>,
+    {
+        parse_on(self, tokens, actions)
+    }
+
+    /// Parse a sequence of tokens, recovering from syntax errors as
+    /// [`LR1ParseTable::parse_with_recovery`](../struct.LR1ParseTable.html#method.parse_with_recovery).
+    ///
+    /// The reductions applied before unwinding are those of the consistent states.
+    pub fn parse_with_recovery<I, Ac>(
+        &self,
+        tokens: I,
+        actions: &mut Ac,
+    ) -> Result<Ac::Value, SyntaxError<Ac::Token>>
+    where
+        I: IntoIterator<Item = Ac::Token>,
+        Ac: Recovery<'a, T, N, A>,
+    {
+        recover_on(self, tokens, actions)
+    }
+
+    /// Return the lookahead tokens (`None` for EOF) accepted on the stack of states at a syntax
+    /// error, as
+    /// [`LR1ParseTable::expected_on_stack`](../struct.LR1ParseTable.html#method.expected_on_stack).
+    ///
+    /// The lookaheads of a default reduction are not stored, so the candidates are the lookaheads
+    /// of the states reached by the default reductions.
+    pub fn expected_on_stack<Tok>(&self, error: &SyntaxError<Tok>) -> BTreeSet<Option<&'a T>> {
+        self.expected_on(&error.stack)
+    }
+}
+
+/// The lookups of the parser in a parse table.
+pub(crate) trait Lookup<'a, T: 'a, N: 'a, A: 'a> {
+    /// Return the action in a state for the given lookahead token (`None` for EOF), if any.
+    fn action(&self, state: usize, lookahead: Option<&T>) -> Option<&LRAction<'a, T, N, A>>;
+
+    /// Return the action of a state that doesn't need a lookahead, if it is consistent.
+    fn consistent(&self, state: usize) -> Option<&LRAction<'a, T, N, A>>;
+
+    /// Return the reduction a state performs on every lookahead it accepts, if it has no other
+    /// action. The reduction is pending whatever token follows.
+    fn pending_reduction(&self, state: usize) -> Option<&LRAction<'a, T, N, A>>;
+
+    /// Return the state to jump to from a state after reducing to `lhs`.
+    fn goto(&self, state: usize, lhs: &N) -> usize;
+
+    /// Return the lookahead tokens (`None` for EOF) that may be accepted on a stack of states.
+    fn candidates(&self, states: &[usize]) -> BTreeSet<Option<&'a T>>;
+
+    /// Return the lookahead tokens (`None` for EOF) accepted on a stack of states, i.e. shifted or
+    /// accepted after the reductions they cause.
+    fn expected_on(&self, states: &[usize]) -> BTreeSet<Option<&'a T>>
+    where
+        T: Ord,
+    {
+        self.candidates(states)
+            .into_iter()
+            .filter(|&t| self.simulate(&mut states.to_vec(), t).is_some())
+            .collect()
+    }
+
+    /// Simulate the parse of a lookahead (`None` for EOF) on a stack of states, up to its shift.
+    /// Returns `None` at a syntax error.
+    fn simulate(&self, states: &mut Vec<usize>, lookahead: Option<&T>) -> Option<Outcome> {
+        loop {
+            let state = *states.last().unwrap();
+            match self.action(state, lookahead) {
+                Some(&LRAction::Shift(target)) => {
+                    states.push(target);
+                    return Some(Outcome::Shift);
+                }
+                Some(&LRAction::Reduce(lhs, rhs)) => {
+                    let len = states.len() - rhs.syms.len();
+                    states.truncate(len);
+                    let target = self.goto(states[len - 1], lhs);
+                    states.push(target);
+                }
+                Some(&LRAction::Accept) => return Some(Outcome::Accept),
+                Some(&LRAction::Error) | None => return None,
+            }
+        }
+    }
+}
+
+impl<'a, T: Ord, N: Ord, A> Lookup<'a, T, N, A> for LR1ParseTable<'a, T, N, A> {
+    fn action(&self, state: usize, lookahead: Option<&T>) -> Option<&LRAction<'a, T, N, A>> {
+        self.states[state].action(lookahead)
+    }
+
+    fn consistent(&self, _state: usize) -> Option<&LRAction<'a, T, N, A>> {
+        None
+    }
+
+    fn pending_reduction(&self, state: usize) -> Option<&LRAction<'a, T, N, A>> {
+        let state = &self.states[state];
+        let mut actions = state.lookahead.values().chain(state.eof.as_ref());
+        let first = actions.next()?;
+        match *first {
+            LRAction::Reduce(_, rhs) => {
+                let same = |action: &LRAction<'a, T, N, A>| match *action {
+                    LRAction::Reduce(_, r) => std::ptr::eq(r, rhs),
+                    _ => false,
+                };
+                if actions.all(same) {
+                    Some(first)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn goto(&self, state: usize, lhs: &N) -> usize {
+        self.states[state].goto[lhs]
+    }
+
+    fn candidates(&self, states: &[usize]) -> BTreeSet<Option<&'a T>> {
+        self.states[*states.last().unwrap()].expected()
+    }
+}
+
+impl<'a, T: Ord, N: Ord, A> Lookup<'a, T, N, A> for DefaultReductionTable<'a, T, N, A> {
+    fn action(&self, state: usize, lookahead: Option<&T>) -> Option<&LRAction<'a, T, N, A>> {
+        DefaultReductionTable::action(self, state, lookahead)
+    }
+
+    fn consistent(&self, state: usize) -> Option<&LRAction<'a, T, N, A>> {
+        if self.is_consistent(state) {
+            self.defaults[state].as_ref()
+        } else {
+            None
+        }
+    }
+
+    fn pending_reduction(&self, state: usize) -> Option<&LRAction<'a, T, N, A>> {
+        self.consistent(state)
+    }
+
+    fn goto(&self, state: usize, lhs: &N) -> usize {
+        self.table.states[state].goto[lhs]
+    }
+
+    fn candidates(&self, states: &[usize]) -> BTreeSet<Option<&'a T>> {
+        // The lookaheads of a default reduction are not stored, so collect those of the states
+        // reached by the default reductions.
+        let mut candidates = BTreeSet::new();
+        let mut reduced = states.to_vec();
+        loop {
+            let state = *reduced.last().unwrap();
+            candidates.extend(self.table.states[state].expected());
+            match self.defaults[state] {
+                Some(LRAction::Reduce(lhs, rhs)) => {
+                    let len = reduced.len() - rhs.syms.len();
+                    reduced.truncate(len);
+                    let target = self.goto(reduced[len - 1], lhs);
+                    reduced.push(target);
+                }
+                _ => break,
+            }
+        }
+        candidates
+    }
+}
+
+/// Parse a sequence of tokens on a parse table, computing the values with the given `Actions`.
+fn parse_on<'a, T: 'a, N: 'a, A: 'a, L, I, Ac>(
+    table: &L,
+    tokens: I,
+    actions: &mut Ac,
+) -> Result<Ac::Value, SyntaxError<Ac::Token>>
+where
+    L: Lookup<'a, T, N, A>,
+    I: IntoIterator<Item = Ac::Token>,
+    Ac: Actions<'a, T, N, A>,
+{
+    // The next token is only read when the state needs a lookahead.
+    let mut tokens = tokens.into_iter().peekable();
+    let mut stack = Stack::new();
+    loop {
+        let state = stack.state();
+        let action = match table.consistent(state) {
+            Some(action) => Some(action),
+            None => {
+                let lookahead = tokens.peek().map(|token| actions.terminal(token));
+                table.action(state, lookahead.as_ref())
+            }
+        };
+        match action {
+            Some(&LRAction::Shift(target)) => {
+                stack.push(target, actions.shift(tokens.next().unwrap()));
+            }
+            Some(&LRAction::Reduce(lhs, rhs)) => stack.reduce(table, lhs, rhs, actions),
+            Some(&LRAction::Accept) => return Ok(stack.values.pop().unwrap()),
+            Some(&LRAction::Error) | None => {
+                return Err(SyntaxError {
+                    state,
+                    token: tokens.next(),
+                    stack: stack.states,
+                })
+            }
+        }
+    }
+}
+
+/// Parse a sequence of tokens on a parse table, computing the values with the given `Recovery` and
+/// recovering from syntax errors in the panic mode of Yacc.
+fn recover_on<'a, T: 'a, N: 'a, A: 'a, L, I, Ac>(
+    table: &L,
+    tokens: I,
+    actions: &mut Ac,
+) -> Result<Ac::Value, SyntaxError<Ac::Token>>
+where
+    L: Lookup<'a, T, N, A>,
+    I: IntoIterator<Item = Ac::Token>,
+    Ac: Recovery<'a, T, N, A>,
+{
+    let error = actions.error_terminal();
+    let mut tokens = tokens.into_iter();
+    let mut next = tokens.next();
+    let mut stack = Stack::new();
+    // The number of tokens to shift before errors are reported again
+    let mut quiet: usize = 0;
+    loop {
+        let state = stack.state();
+        let lookahead = next.as_ref().map(|token| actions.terminal(token));
+        match table.action(state, lookahead.as_ref()) {
+            Some(&LRAction::Shift(target)) => {
+                stack.push(target, actions.shift(next.take().unwrap()));
+                next = tokens.next();
+                quiet = quiet.saturating_sub(1);
+            }
+            Some(&LRAction::Reduce(lhs, rhs)) => stack.reduce(table, lhs, rhs, actions),
+            Some(&LRAction::Accept) => return Ok(stack.values.pop().unwrap()),
+            Some(&LRAction::Error) | None if quiet == ERROR_RECOVERY_TOKENS => {
+                // Nothing was shifted after `error`, so discard the token.
+                match next.take() {
+                    Some(token) => {
+                        actions.on_discard(token);
+                        next = tokens.next();
+                    }
+                    None => {
+                        return Err(SyntaxError {
+                            state,
+                            token: None,
+                            stack: stack.states,
+                        })
+                    }
+                }
+            }
+            Some(&LRAction::Error) | None => {
+                if quiet == 0 {
+                    actions.on_syntax_error(state, next.as_ref());
+                }
+                quiet = ERROR_RECOVERY_TOKENS;
+                // Apply the reductions that are pending whatever the token, so the phrases
+                // completed before the error are not popped.
+                while let Some(&LRAction::Reduce(lhs, rhs)) = table.pending_reduction(stack.state())
+                {
+                    stack.reduce(table, lhs, rhs, actions);
+                }
+                // Find the state to shift `error` in before popping, so the stack is left
+                // as it is if there is none.
+                let shift =
+                    stack
+                        .states
+                        .iter()
+                        .enumerate()
+                        .rev()
+                        .find_map(|(i, &state)| match table.action(state, Some(&error)) {
+                            Some(&LRAction::Shift(target)) => Some((i, target)),
+                            _ => None,
+                        });
+                let (depth, target) = match shift {
+                    Some(shift) => shift,
+                    None => {
+                        return Err(SyntaxError {
+                            state,
+                            token: next,
+                            stack: stack.states,
+                        })
+                    }
+                };
+                stack.states.truncate(depth + 1);
+                let popped = stack.values.split_off(depth);
+                stack.push(target, actions.error_value(popped));
+            }
+        }
+    }
+}
+
+/// The outcome of a lookahead on a stack of states.
+pub(crate) enum Outcome {
+    Shift,
+    Accept,
+}
+
+/// The number of tokens to shift after an error before errors are reported again.
+const ERROR_RECOVERY_TOKENS: usize = 3;
+
+/// The stack of states and the parallel stack of values of a parse.
+pub(crate) struct Stack<V> {
+    pub(crate) states: Vec<usize>,
+    pub(crate) values: Vec<V>,
+}
+
+impl<V> Stack<V> {
+    pub(crate) fn new() -> Self {
+        Stack {
+            states: vec![0],
+            values: vec![],
+        }
+    }
+
+    /// Return the current state.
+    pub(crate) fn state(&self) -> usize {
+        *self.states.last().unwrap()
+    }
+
+    /// Push a state with the value of the symbol leading to it.
+    pub(crate) fn push(&mut self, state: usize, value: V) {
+        self.states.push(state);
+        self.values.push(value);
+    }
+
+    /// Reduce by the rule `lhs -> rhs`, replacing the values of the right-hand side by the value
+    /// computed by the actions.
+    pub(crate) fn reduce<'a, T: 'a, N: 'a, A: 'a, L, Ac>(
+        &mut self,
+        table: &L,
+        lhs: &'a N,
+        rhs: &'a Rhs<T, N, A>,
+        actions: &mut Ac,
+    ) where
+        L: Lookup<'a, T, N, A>,
+        Ac: Actions<'a, T, N, A, Value = V>,
+    {
+        let len = rhs.syms.len();
+        self.states.truncate(self.states.len() - len);
+        let children = self.values.split_off(self.values.len() - len);
+        let value = actions.reduce(lhs, rhs, children);
+        let target = table.goto(self.state(), lhs);
+        self.push(target, value);
+    }
+}
@@ -0,0 +1,279 @@
+//! This module provides a push parser, which is fed the tokens one at a time instead of pulling
+//! them from an iterator.
+//!
+//! The stack of the parser is a persistent linked list, so a [`Snapshot`](struct.Snapshot.html)
+//! of it is taken in constant time and shares its frames with the parser. Restoring a snapshot
+//! rolls the parse back to a checkpoint, e.g. after trying a token speculatively.
+//!
+//! ```ignore
+//! let mut parser = PushParser::new(&table, actions);
+//! let checkpoint = parser.snapshot();
+//! for token in tokens {
+//!     parser.feed(token)?;
+//! }
+//! parser.restore(checkpoint);
+//! ```
+//!
+use crate::parser::{Actions, Lookup, SyntaxError};
+use crate::{DefaultReductionTable, LR1ParseTable, LRAction, Rhs};
+use std::collections::BTreeSet;
+use std::rc::Rc;
+
+/// A frame of the stack, holding a state and the value of the symbol leading to it.
+struct Frame<V> {
+    state: usize,
+    value: V,
+    /// The frame below this one, `None` for the initial state.
+    below: Option<Rc<Frame<V>>>,
+}
+
+/// A snapshot of the stack of a push parser.
+///
+/// Cloning a snapshot is cheap: it only clones a reference to the top frame.
+pub struct Snapshot<V> {
+    top: Option<Rc<Frame<V>>>,
+}
+
+impl<V> Snapshot<V> {
+    /// Create the stack with only the initial state.
+    fn new() -> Self {
+        Snapshot { top: None }
+    }
+
+    /// Return the current state.
+    pub fn state(&self) -> usize {
+        self.top.as_ref().map_or(0, |frame| frame.state)
+    }
+
+    /// Return the states of the stack, from the initial state to the current state.
+    pub fn states(&self) -> Vec<usize> {
+        let mut states = vec![];
+        let mut frame = self.top.as_deref();
+        while let Some(f) = frame {
+            states.push(f.state);
+            frame = f.below.as_deref();
+        }
+        states.push(0);
+        states.reverse();
+        states
+    }
+
+    fn push(&mut self, state: usize, value: V) {
+        let below = self.top.take();
+        self.top = Some(Rc::new(Frame {
+            state,
+            value,
+            below,
+        }));
+    }
+
+    /// Pop the top frame and return its value, cloned if the frame is shared with a snapshot.
+    fn pop(&mut self) -> V
+    where
+        V: Clone,
+    {
+        match Rc::try_unwrap(self.top.take().unwrap()) {
+            Ok(mut frame) => {
+                self.top = frame.below.take();
+                frame.value
+            }
+            Err(frame) => {
+                self.top = frame.below.clone();
+                frame.value.clone()
+            }
+        }
+    }
+}
+
+impl<V> Clone for Snapshot<V> {
+    fn clone(&self) -> Self {
+        Snapshot {
+            top: self.top.clone(),
+        }
+    }
+}
+
+impl<V> Drop for Snapshot<V> {
+    // Drop the frames iteratively, as dropping a long list recursively would overflow the stack.
+    fn drop(&mut self) {
+        let mut top = self.top.take();
+        while let Some(frame) = top {
+            top = match Rc::try_unwrap(frame) {
+                Ok(mut frame) => frame.below.take(),
+                Err(_) => None,
+            };
+        }
+    }
+}
+
+/// A parser fed one token at a time.
+///
+/// The values are computed by the `Actions` owned by the parser. They must be `Clone`, because
+/// a reduction popping frames shared with a snapshot clones their values; wrap them in an `Rc` if
+/// they are expensive to clone.
+pub struct PushParser<'t, 'a: 't, T: 'a, N: 'a, A: 'a, Ac>
+where
+    Ac: Actions<'a, T, N, A>,
+{
+    table: &'t dyn Lookup<'a, T, N, A>,
+    actions: Ac,
+    stack: Snapshot<Ac::Value>,
+}
+
+impl<'t, 'a, T: Ord, N: Ord, A, Ac> PushParser<'t, 'a, T, N, A, Ac>
+where
+    Ac: Actions<'a, T, N, A>,
+    Ac::Value: Clone,
+{
+    /// Create a parser in the initial state of the table.
+    pub fn new(table: &'t LR1ParseTable<'a, T, N, A>, actions: Ac) -> Self {
+        PushParser {
+            table,
+            actions,
+            stack: Snapshot::new(),
+        }
+    }
+
+    /// Create a parser in the initial state of a table with default reductions.
+    ///
+    /// A token is only fed if it is shifted after the default reductions it causes, so a syntax
+    /// error still leaves the parser unchanged.
+    pub fn with_default_reductions(
+        table: &'t DefaultReductionTable<'a, T, N, A>,
+        actions: Ac,
+    ) -> Self {
+        PushParser {
+            table,
+            actions,
+            stack: Snapshot::new(),
+        }
+    }
+
+    /// Return the actions of the parser.
+    pub fn actions(&self) -> &Ac {
+        &self.actions
+    }
+
+    /// Return the actions of the parser, mutably.
+    pub fn actions_mut(&mut self) -> &mut Ac {
+        &mut self.actions
+    }
+
+    /// Return the current state.
+    pub fn state(&self) -> usize {
+        self.stack.state()
+    }
+
+    /// Return the lookahead tokens (`None` for EOF) accepted in the current state, following the
+    /// reductions as [`expected_on_stack`](../struct.LR1ParseTable.html#method.expected_on_stack).
+    pub fn expected(&self) -> BTreeSet<Option<&'a T>> {
+        self.table
+            .candidates(&self.stack.states())
+            .into_iter()
+            .filter(|&t| self.accepts(t))
+            .collect()
+    }
+
+    /// Return a snapshot of the stack.
+    pub fn snapshot(&self) -> Snapshot<Ac::Value> {
+        self.stack.clone()
+    }
+
+    /// Restore a snapshot of the stack. The actions are not restored.
+    pub fn restore(&mut self, snapshot: Snapshot<Ac::Value>) {
+        self.stack = snapshot;
+    }
+
+    /// Feed the next token to the parser, doing the reductions it causes and shifting it.
+    ///
+    /// At a syntax error, the token is returned in the error and the parser is left unchanged, so
+    /// another token can be fed instead.
+    pub fn feed(&mut self, token: Ac::Token) -> Result<(), SyntaxError<Ac::Token>> {
+        let terminal = self.actions.terminal(&token);
+        if !self.accepts(Some(&terminal)) {
+            return Err(SyntaxError {
+                state: self.state(),
+                token: Some(token),
+                stack: self.stack.states(),
+            });
+        }
+        let table = self.table;
+        loop {
+            match table.action(self.state(), Some(&terminal)) {
+                Some(&LRAction::Shift(target)) => {
+                    let value = self.actions.shift(token);
+                    self.stack.push(target, value);
+                    return Ok(());
+                }
+                Some(&LRAction::Reduce(lhs, rhs)) => self.reduce(lhs, rhs),
+                _ => unreachable!("the token is accepted"),
+            }
+        }
+    }
+
+    /// End the input, doing the remaining reductions, and return the value of the symbol of the
+    /// start rule.
+    ///
+    /// The parser is then back in its initial state. At a syntax error, the parser is left
+    /// unchanged, so more tokens can be fed.
+    pub fn finish(&mut self) -> Result<Ac::Value, SyntaxError<Ac::Token>> {
+        if !self.accepts(None) {
+            return Err(SyntaxError {
+                state: self.state(),
+                token: None,
+                stack: self.stack.states(),
+            });
+        }
+        let table = self.table;
+        loop {
+            match table.action(self.state(), None) {
+                Some(&LRAction::Reduce(lhs, rhs)) => self.reduce(lhs, rhs),
+                Some(&LRAction::Accept) => {
+                    let value = self.stack.pop();
+                    self.stack = Snapshot::new();
+                    return Ok(value);
+                }
+                _ => unreachable!("EOF is accepted"),
+            }
+        }
+    }
+
+    /// Reduce by the rule `lhs -> rhs`.
+    fn reduce(&mut self, lhs: &'a N, rhs: &'a Rhs<T, N, A>) {
+        let mut children: Vec<_> = rhs.syms.iter().map(|_| self.stack.pop()).collect();
+        children.reverse();
+        let value = self.actions.reduce(lhs, rhs, children);
+        let target = self.table.goto(self.state(), lhs);
+        self.stack.push(target, value);
+    }
+
+    /// Check whether the lookahead (`None` for EOF) is shifted or accepted after the reductions it
+    /// causes, without changing the stack.
+    fn accepts(&self, lookahead: Option<&T>) -> bool {
+        // The states pushed by the reductions, above the remaining frames of the stack
+        let mut pushed = vec![];
+        let mut below = self.stack.top.as_deref();
+        loop {
+            let state = match pushed.last() {
+                Some(&state) => state,
+                None => below.map_or(0, |frame| frame.state),
+            };
+            match self.table.action(state, lookahead) {
+                Some(&LRAction::Shift(_)) | Some(&LRAction::Accept) => return true,
+                Some(&LRAction::Reduce(lhs, rhs)) => {
+                    for _ in rhs.syms.iter() {
+                        if pushed.pop().is_none() {
+                            below = below.and_then(|frame| frame.below.as_deref());
+                        }
+                    }
+                    let state = match pushed.last() {
+                        Some(&state) => state,
+                        None => below.map_or(0, |frame| frame.state),
+                    };
+                    pushed.push(self.table.goto(state, lhs));
+                }
+                Some(&LRAction::Error) | None => return false,
+            }
+        }
+    }
+}
@@ -0,0 +1,302 @@
+//! This module provides automatic repair of syntax errors, in the style of the CPCT+ algorithm of
+//! Diekmann and Tratt.
+//!
+//! At a syntax error, the parser searches for a sequence of insertions and deletions of tokens of
+//! minimal cost, after which it can shift three more tokens of the input or accept. Each insertion
+//! and each deletion costs one. The repairs are reported to the
+//! [`Repairer`](trait.Repairer.html), applied to the input, and the parse continues.
+//!
+//! The search only simulates the parse table on a copy of the stack of states, so it needs no
+//! information beyond the [`LR1ParseTable`](../struct.LR1ParseTable.html), or the
+//! [`DefaultReductionTable`](../struct.DefaultReductionTable.html). It is bounded by the cost
+//! of the repairs and by the number of configurations it explores, rather than by time, and it
+//! explores the configurations in a fixed order: the terminals to insert in the order of the parse
+//! table, then the deletion. The first repair found among those of minimal cost is applied, so the
+//! repairs of an input are always the same.
+//!
+use crate::parser::{Actions, Lookup, Outcome, Stack, SyntaxError};
+use crate::{DefaultReductionTable, LR1ParseTable, LRAction};
+use std::collections::{BTreeSet, VecDeque};
+use std::fmt::{self, Display};
+
+/// A repair of the input at a syntax error.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Repair<'a, T: 'a, Tok> {
+    /// A token of the terminal was inserted.
+    Insert(&'a T),
+    /// The token was deleted.
+    Delete(Tok),
+    /// The next token of the input was kept, between the other repairs.
+    Shift,
+}
+
+impl<'a, T: Display, Tok: Display> Display for Repair<'a, T, Tok> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Repair::Insert(t) => write!(f, "inserted `{}`", t),
+            Repair::Delete(ref token) => write!(f, "deleted `{}`", token),
+            Repair::Shift => write!(f, "shifted"),
+        }
+    }
+}
+
+/// The `Actions` of a parse that repairs syntax errors, see
+/// [`parse_with_repair`](../struct.LR1ParseTable.html#method.parse_with_repair).
+pub trait Repairer<'a, T, N, A>: Actions<'a, T, N, A> {
+    /// Create a token of a terminal inserted by a repair.
+    fn insert(&mut self, terminal: &'a T) -> Self::Token;
+
+    /// Called with the repairs of a syntax error found in `state`, before they are applied.
+    ///
+    /// The repairs are in the order of the input, starting at the token of the error, and
+    /// include the deleted tokens.
+    fn on_repair(&mut self, _state: usize, _repairs: Vec<Repair<'a, T, Self::Token>>) {}
+}
+
+/// The number of tokens of the input to shift after a repair for it to be complete.
+const REPAIR_SHIFTS: usize = 3;
+
+/// The maximal cost of the repairs of a syntax error.
+const MAX_REPAIR_COST: usize = 3;
+
+/// The maximal number of configurations explored by the search for repairs of a syntax error.
+const MAX_REPAIR_CONFIGURATIONS: usize = 10_000;
+
+/// The number of tokens read ahead by the search for repairs, enough for repairs of the maximal
+/// cost.
+const REPAIR_LOOKAHEAD: usize = (MAX_REPAIR_COST + 1) * REPAIR_SHIFTS;
+
+/// A step of a repair sequence.
+enum Step<'a, T: 'a> {
+    Insert(&'a T),
+    Delete,
+    Shift,
+}
+
+impl<'a, T> Clone for Step<'a, T> {
+    fn clone(&self) -> Self {
+        match *self {
+            Step::Insert(t) => Step::Insert(t),
+            Step::Delete => Step::Delete,
+            Step::Shift => Step::Shift,
+        }
+    }
+}
+
+/// A configuration of the search for repairs.
+struct Configuration<'a, T: 'a> {
+    /// The stack of states.
+    states: Vec<usize>,
+    /// The position of the next token in the lookahead.
+    pos: usize,
+    /// The number of tokens shifted since the last insertion or deletion.
+    shifts: usize,
+    steps: Vec<Step<'a, T>>,
+}
+
+impl<'a, T> Configuration<'a, T> {
+    /// Return the configuration after the step, with the given stack of states.
+    fn step(&self, states: Vec<usize>, step: Step<'a, T>) -> Self {
+        let (pos, shifts) = match step {
+            Step::Insert(_) => (self.pos, 0),
+            Step::Delete => (self.pos + 1, 0),
+            Step::Shift => (self.pos + 1, self.shifts + 1),
+        };
+        let mut steps = self.steps.clone();
+        steps.push(step);
+        Configuration {
+            states,
+            pos,
+            shifts,
+            steps,
+        }
+    }
+}
+
+impl<'a, T: Ord, N: Ord, A> LR1ParseTable<'a, T, N, A> {
+    /// Parse a sequence of tokens, computing the values with the given `Repairer` and repairing
+    /// syntax errors by inserting and deleting tokens.
+    ///
+    /// Returns the value of the symbol of the start rule, or the first syntax error for which no
+    /// repair was found within the bounds of the search.
+    pub fn parse_with_repair<I, Ac>(
+        &self,
+        tokens: I,
+        actions: &mut Ac,
+    ) -> Result<Ac::Value, SyntaxError<Ac::Token>>
+    where
+        I: IntoIterator<Item = Ac::Token>,
+        Ac: Repairer<'a, T, N, A>,
+    {
+        repair_on(self, tokens, actions)
+    }
+}
+
+impl<'a, T: Ord, N: Ord, A> DefaultReductionTable<'a, T, N, A> {
+    /// Parse a sequence of tokens, repairing syntax errors as
+    /// [`LR1ParseTable::parse_with_repair`](../struct.LR1ParseTable.html#method.parse_with_repair).
+    pub fn parse_with_repair<I, Ac>(
+        &self,
+        tokens: I,
+        actions: &mut Ac,
+    ) -> Result<Ac::Value, SyntaxError<Ac::Token>>
+    where
+        I: IntoIterator<Item = Ac::Token>,
+        Ac: Repairer<'a, T, N, A>,
+    {
+        repair_on(self, tokens, actions)
+    }
+}
+
+/// Parse a sequence of tokens on a parse table, computing the values with the given `Repairer`
+/// and repairing syntax errors.
+fn repair_on<'a, T: Ord + 'a, N: 'a, A: 'a, L, I, Ac>(
+    table: &L,
+    tokens: I,
+    actions: &mut Ac,
+) -> Result<Ac::Value, SyntaxError<Ac::Token>>
+where
+    L: Lookup<'a, T, N, A>,
+    I: IntoIterator<Item = Ac::Token>,
+    Ac: Repairer<'a, T, N, A>,
+{
+    let mut tokens = tokens.into_iter().fuse();
+    // The tokens to parse before the rest of the input
+    let mut pending = VecDeque::new();
+    let mut next = tokens.next();
+    let mut stack = Stack::new();
+    loop {
+        let state = stack.state();
+        let lookahead = next.as_ref().map(|token| actions.terminal(token));
+        match table.action(state, lookahead.as_ref()) {
+            Some(&LRAction::Shift(target)) => {
+                stack.push(target, actions.shift(next.take().unwrap()));
+                next = pending.pop_front().or_else(|| tokens.next());
+            }
+            Some(&LRAction::Reduce(lhs, rhs)) => stack.reduce(table, lhs, rhs, actions),
+            Some(&LRAction::Accept) => return Ok(stack.values.pop().unwrap()),
+            Some(&LRAction::Error) | None => {
+                let mut input: VecDeque<_> = next.take().into_iter().collect();
+                input.append(&mut pending);
+                while input.len() < REPAIR_LOOKAHEAD {
+                    match tokens.next() {
+                        Some(token) => input.push_back(token),
+                        None => break,
+                    }
+                }
+                let mut lookahead: Vec<_> = input
+                    .iter()
+                    .map(|token| Some(actions.terminal(token)))
+                    .collect();
+                if input.len() < REPAIR_LOOKAHEAD {
+                    lookahead.push(None);
+                }
+                let mut steps = match search(table, &stack.states, &lookahead) {
+                    Some(steps) => steps,
+                    None => {
+                        return Err(SyntaxError {
+                            state,
+                            token: input.pop_front(),
+                            stack: stack.states,
+                        })
+                    }
+                };
+                while let Some(&Step::Shift) = steps.last() {
+                    steps.pop();
+                }
+
+                let mut repairs = vec![];
+                for step in steps {
+                    match step {
+                        Step::Insert(t) => {
+                            pending.push_back(actions.insert(t));
+                            repairs.push(Repair::Insert(t));
+                        }
+                        Step::Delete => repairs.push(Repair::Delete(input.pop_front().unwrap())),
+                        Step::Shift => {
+                            pending.push_back(input.pop_front().unwrap());
+                            repairs.push(Repair::Shift);
+                        }
+                    }
+                }
+                actions.on_repair(state, repairs);
+                pending.append(&mut input);
+                next = pending.pop_front().or_else(|| tokens.next());
+            }
+        }
+    }
+}
+
+/// Search for the repairs of a syntax error on the stack of states, with the terminals of the
+/// next tokens as lookahead, ending with `None` if the input ends.
+///
+/// The configurations are explored by increasing cost, and in the order they were found for
+/// the same cost.
+fn search<'a, T: Ord + 'a, N: 'a, A: 'a, L>(
+    table: &L,
+    states: &[usize],
+    lookahead: &[Option<T>],
+) -> Option<Vec<Step<'a, T>>>
+where
+    L: Lookup<'a, T, N, A>,
+{
+    let mut todo = vec![Configuration {
+        states: states.to_vec(),
+        pos: 0,
+        shifts: 0,
+        steps: vec![],
+    }];
+    let mut seen = BTreeSet::new();
+    let mut explored = 0;
+    for cost in 0..MAX_REPAIR_COST + 1 {
+        let mut costlier = vec![];
+        let mut i = 0;
+        while i < todo.len() {
+            explored += 1;
+            if explored > MAX_REPAIR_CONFIGURATIONS {
+                return None;
+            }
+            let conf = &todo[i];
+            i += 1;
+            let token = match lookahead.get(conf.pos) {
+                Some(token) => token.as_ref(),
+                // All the tokens read ahead were shifted
+                None => return Some(conf.steps.clone()),
+            };
+
+            let mut states = conf.states.clone();
+            let shifted = match table.simulate(&mut states, token) {
+                Some(Outcome::Shift) => {
+                    let next = conf.step(states, Step::Shift);
+                    if next.shifts == REPAIR_SHIFTS {
+                        return Some(next.steps);
+                    }
+                    Some(next)
+                }
+                Some(Outcome::Accept) => return Some(conf.steps.clone()),
+                None => None,
+            };
+            if cost < MAX_REPAIR_COST {
+                for t in table.candidates(&conf.states).into_iter().flatten() {
+                    let mut states = conf.states.clone();
+                    if let Some(Outcome::Shift) = table.simulate(&mut states, Some(t)) {
+                        costlier.push(conf.step(states, Step::Insert(t)));
+                    }
+                }
+                if token.is_some() {
+                    costlier.push(conf.step(conf.states.clone(), Step::Delete));
+                }
+            }
+            if let Some(next) = shifted {
+                if seen.insert((next.states.clone(), next.pos, next.shifts)) {
+                    todo.push(next);
+                }
+            }
+        }
+        todo = costlier
+            .into_iter()
+            .filter(|conf| seen.insert((conf.states.clone(), conf.pos, conf.shifts)))
+            .collect();
+    }
+    None
+}
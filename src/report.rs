@@ -0,0 +1,211 @@
+//! This module provides human-readable reports of conflicts, similar to the `.output` file of
+//! Bison.
+//!
+//! A report shows the state of the conflict with a shortest path of symbols leading to it, the
+//! items of the state with the dot at the current position, and the competing actions on the
+//! conflicting lookahead. As in Bison, an action that is not taken is shown in brackets.
+//!
+//! ```text
+//! State 8 conflict: shift/reduce on else
+//!
+//!     path: if ( Cond ) Stmt
+//!
+//!     Stmt → if ( Cond ) Stmt •
+//!     Stmt → if ( Cond ) Stmt • else Stmt
+//!
+//!     else  shift, and go to state 9
+//!     else  [reduce using Stmt → if ( Cond ) Stmt]
+//! ```
+//!
+use crate::{
+    LR0StateMachine, LR1Conflict, LR1ResolvedConflict, LRConflictResolution, Rhs, Terminal,
+};
+use std::fmt::{self, Display};
+
+impl Display for LRConflictResolution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            LRConflictResolution::ShiftOverReduce => "shift",
+            LRConflictResolution::ReduceFirstRule => "reduce using the first rule",
+            LRConflictResolution::ReduceSecondRule => "reduce using the second rule",
+            LRConflictResolution::ShiftByPrecedence => "shift by precedence",
+            LRConflictResolution::ReduceByPrecedence => "reduce by precedence",
+            LRConflictResolution::ErrorByNonassociativity => "an error (nonassociative)",
+        })
+    }
+}
+
+/// A report of a conflict, implementing `Display`.
+///
+/// Created by [`LR0StateMachine::report`](../struct.LR0StateMachine.html#method.report) and
+/// [`LR0StateMachine::report_resolved`](../struct.LR0StateMachine.html#method.report_resolved).
+#[derive(Debug)]
+pub struct ConflictReport<'r, 'a: 'r, T: 'a, N: 'a, A: 'a> {
+    state_machine: &'r LR0StateMachine<'a, T, N, A>,
+    conflict: &'r LR1Conflict<'a, T, N, A>,
+    resolution: Option<&'r LRConflictResolution>,
+}
+
+impl<'a, T: Ord, N: Ord, A> LR0StateMachine<'a, T, N, A> {
+    /// Create a report of an unresolved conflict in this state machine.
+    ///
+    /// The state of the conflict is numbered as in this state machine, also for conflicts of the
+    /// LR(1) constructions, whose states are split from these states.
+    pub fn report<'r>(
+        &'r self,
+        conflict: &'r LR1Conflict<'a, T, N, A>,
+    ) -> ConflictReport<'r, 'a, T, N, A> {
+        ConflictReport {
+            state_machine: self,
+            conflict,
+            resolution: None,
+        }
+    }
+
+    /// Create a report of a resolved conflict in this state machine.
+    pub fn report_resolved<'r>(
+        &'r self,
+        resolved: &'r LR1ResolvedConflict<'a, T, N, A>,
+    ) -> ConflictReport<'r, 'a, T, N, A> {
+        ConflictReport {
+            state_machine: self,
+            conflict: &resolved.conflict,
+            resolution: Some(&resolved.applied_resolution),
+        }
+    }
+}
+
+impl<'r, 'a, T: Ord + Display, N: Ord + Display, A> Display for ConflictReport<'r, 'a, T, N, A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (item_set, token, kind) = match *self.conflict {
+            LR1Conflict::ReduceReduce {
+                ref state, token, ..
+            } => (state, token, "reduce/reduce"),
+            LR1Conflict::ShiftReduce {
+                ref state, token, ..
+            } => (state, token, "shift/reduce"),
+        };
+        let token = Lookahead(token);
+        let state = self
+            .state_machine
+            .states
+            .iter()
+            .position(|(iset, _)| iset == item_set);
+        match state {
+            Some(state) => write!(f, "State {}", state)?,
+            None => write!(f, "Unknown state")?,
+        }
+        let resolved = if self.resolution.is_some() {
+            " resolved"
+        } else {
+            ""
+        };
+        writeln!(f, "{} conflict: {} on {}", resolved, kind, token)?;
+        writeln!(f)?;
+        if let Some(path) = state.and_then(|state| self.state_machine.shortest_path(state)) {
+            write!(f, "    path:")?;
+            for sym in path {
+                write!(f, " {}", sym)?;
+            }
+            writeln!(f)?;
+            writeln!(f)?;
+        }
+
+        // The kernel items and the completed items of the conflict
+        for item in item_set.items.iter() {
+            if item.pos > 0
+                || item.pos == item.rhs.syms.len()
+                || *item.lhs == *self.state_machine.start
+            {
+                writeln!(f, "    {}", item)?;
+            }
+        }
+        writeln!(f)?;
+
+        match *self.conflict {
+            LR1Conflict::ReduceReduce { r1, r2, .. } => {
+                let first_taken = !matches!(
+                    self.resolution,
+                    Some(&LRConflictResolution::ReduceSecondRule)
+                );
+                write_action(f, &token, first_taken, Action::Reduce(r1))?;
+                write_action(f, &token, !first_taken, Action::Reduce(r2))?;
+            }
+            LR1Conflict::ShiftReduce { rule, .. } => {
+                let target = state.and_then(|state| {
+                    self.state_machine.states[state]
+                        .1
+                        .iter()
+                        .find(|&(&sym, _)| match *sym {
+                            Terminal(ref t) => Some(t) == token.0,
+                            _ => false,
+                        })
+                        .map(|(_, &target)| target)
+                });
+                let (shift_taken, reduce_taken) = match self.resolution {
+                    Some(&LRConflictResolution::ReduceByPrecedence) => (false, true),
+                    Some(&LRConflictResolution::ErrorByNonassociativity) => {
+                        writeln!(f, "    {}  error (nonassociative)", token)?;
+                        (false, false)
+                    }
+                    _ => (true, false),
+                };
+                write_action(f, &token, shift_taken, Action::<T, N, A>::Shift(target))?;
+                write_action(f, &token, reduce_taken, Action::Reduce(rule))?;
+            }
+        }
+        if let Some(resolution) = self.resolution {
+            writeln!(f)?;
+            writeln!(f, "    Conflict resolved as {}.", resolution)?;
+        }
+        Ok(())
+    }
+}
+
+/// A lookahead token, displayed as `$end` for EOF.
+struct Lookahead<'a, T: 'a>(Option<&'a T>);
+
+impl<'a, T: Display> Display for Lookahead<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Some(t) => t.fmt(f),
+            None => f.write_str("$end"),
+        }
+    }
+}
+
+/// A competing action of a conflict.
+enum Action<'a, T: 'a, N: 'a, A: 'a> {
+    Shift(Option<usize>),
+    Reduce((&'a N, &'a Rhs<T, N, A>)),
+}
+
+/// Write an action on the token, in brackets if it is not taken.
+fn write_action<T: Display, N: Display, A>(
+    f: &mut fmt::Formatter,
+    token: &Lookahead<T>,
+    taken: bool,
+    action: Action<T, N, A>,
+) -> fmt::Result {
+    write!(f, "    {}  ", token)?;
+    if !taken {
+        write!(f, "[")?;
+    }
+    match action {
+        Action::Shift(Some(target)) => write!(f, "shift, and go to state {}", target)?,
+        Action::Shift(None) => write!(f, "shift")?,
+        Action::Reduce((lhs, rhs)) => {
+            write!(f, "reduce using {} →", lhs)?;
+            for sym in rhs.syms.iter() {
+                write!(f, " {}", sym)?;
+            }
+            if rhs.syms.is_empty() {
+                write!(f, " ε")?;
+            }
+        }
+    }
+    if !taken {
+        write!(f, "]")?;
+    }
+    writeln!(f)
+}
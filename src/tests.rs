@@ -1,4 +1,4 @@
-use crate::config::DefaultConfig;
+use crate::config::{DefaultConfig, PrecedenceConfig};
 
 use super::*;
 use std::collections::BTreeMap;
@@ -98,10 +98,9 @@ fn test_lalr1() {
     assert!(g.lalr1(&c).is_ok());
 }
 
-#[test]
-fn test_lalr1_no_conflict() {
+fn list_grammar() -> Grammar<&'static str, &'static str, ()> {
     // This grammar was taken from https://github.com/jsinger67/parol/blob/main/examples/list_lr/list.par
-    let g = Grammar {
+    Grammar {
         rules: map![
             "List" => vec![
                 rhs(vec![Nonterminal("ListOpt")], ()),
@@ -122,7 +121,12 @@ fn test_lalr1_no_conflict() {
             ]
         ],
         start: "List",
-    };
+    }
+}
+
+#[test]
+fn test_lalr1_no_conflict() {
+    let g = list_grammar();
     let pt_expected = LR1ParseTable::<&str, &str, ()> {
         states: vec![
             LR1State {
@@ -246,6 +250,38 @@ impl<'a> Config<'a, &'a str, &'a str, ()> for TestConfig<'a> {
     }
 }
 
+fn dangling_else_grammar() -> Grammar<&'static str, &'static str, ()> {
+    Grammar {
+        rules: map![
+            "Conflict" => vec![
+                rhs(vec![Nonterminal("Stmt")], ()),
+            ],
+            "Stmt" => vec![
+                rhs(vec![
+                    Terminal("if"),
+                    Terminal("("),
+                    Nonterminal("Cond"),
+                    Terminal(")"),
+                    Nonterminal("Stmt")], ()),
+                rhs(vec![
+                    Terminal("if"),
+                    Terminal("("),
+                    Nonterminal("Cond"),
+                    Terminal(")"),
+                    Nonterminal("Stmt"),
+                    Terminal("else"),
+                    Nonterminal("Stmt")], ()),
+                rhs(vec![], ()),
+            ],
+            "Cond" => vec![
+                rhs(vec![Terminal("true")], ()),
+                rhs(vec![Terminal("false")], ()),
+            ]
+        ],
+        start: "Conflict",
+    }
+}
+
 #[test]
 fn test_lalr1_resolved_shift_reduce_conflict() {
     // The grammar resemples this example
@@ -287,35 +323,7 @@ fn test_lalr1_resolved_shift_reduce_conflict() {
     // corresponds to the first interpretation (the `else` belongs to the inner `if`). This is
     // generally what we want in programming languages -- it's how C, C++, Java, and most other
     // languages handle this ambiguity.
-    let g = Grammar {
-        rules: map![
-            "Conflict" => vec![
-                rhs(vec![Nonterminal("Stmt")], ()),
-            ],
-            "Stmt" => vec![
-                rhs(vec![
-                    Terminal("if"),
-                    Terminal("("),
-                    Nonterminal("Cond"),
-                    Terminal(")"),
-                    Nonterminal("Stmt")], ()),
-                rhs(vec![
-                    Terminal("if"),
-                    Terminal("("),
-                    Nonterminal("Cond"),
-                    Terminal(")"),
-                    Nonterminal("Stmt"),
-                    Terminal("else"),
-                    Nonterminal("Stmt")], ()),
-                rhs(vec![], ()),
-            ],
-            "Cond" => vec![
-                rhs(vec![Terminal("true")], ()),
-                rhs(vec![Terminal("false")], ()),
-            ]
-        ],
-        start: "Conflict",
-    };
+    let g = dangling_else_grammar();
 
     let pt_expected = LR1ParseTable::<&str, &str, ()> {
         states: vec![
@@ -433,3 +441,1527 @@ fn test_lalr1_resolved_shift_reduce_conflict() {
         panic!("Expected a parse table");
     }
 }
+
+fn ambiguous_expression_grammar() -> Grammar<&'static str, &'static str, ()> {
+    Grammar {
+        rules: map![
+            "S" => vec![
+                rhs(vec![Nonterminal("E")], ()),
+            ],
+            "E" => vec![
+                rhs(vec![Nonterminal("E"), Terminal("+"), Nonterminal("E")], ()),
+                rhs(vec![Nonterminal("E"), Terminal("*"), Nonterminal("E")], ()),
+                rhs(vec![Terminal("x")], ()),
+            ]
+        ],
+        start: "S",
+    }
+}
+
+#[test]
+fn test_lalr1_collect_conflicts() {
+    let g = ambiguous_expression_grammar();
+    let c = DefaultConfig::new();
+    assert!(g.lalr1(&c).is_err());
+    let partial = g.lalr1_collect_conflicts(&c).unwrap_err();
+    // Both binary rules conflict with shifting either operator
+    assert_eq!(partial.conflicts.len(), 4);
+    for ((state, token), conflicts) in partial.conflicts.iter() {
+        assert!(token.is_some());
+        assert_eq!(conflicts.len(), 1);
+        match conflicts[0] {
+            LR1Conflict::ShiftReduce { .. } => {}
+            LR1Conflict::ReduceReduce { .. } => panic!("Unexpected reduce-reduce conflict"),
+        }
+        // The partial table keeps the shift
+        match partial.table.states[*state].lookahead[token.unwrap()] {
+            LRAction::Shift(_) => {}
+            ref action => panic!("Expected a shift, got {:?}", action),
+        }
+    }
+}
+
+fn lr1_but_not_lalr1_grammar() -> Grammar<&'static str, &'static str, ()> {
+    // Merging the states after `a e` and `b e` leads to a reduce-reduce conflict.
+    Grammar {
+        rules: map![
+            "S'" => vec![
+                rhs(vec![Nonterminal("S")], ()),
+            ],
+            "S" => vec![
+                rhs(vec![Terminal("a"), Nonterminal("E"), Terminal("c")], ()),
+                rhs(vec![Terminal("a"), Nonterminal("F"), Terminal("d")], ()),
+                rhs(vec![Terminal("b"), Nonterminal("F"), Terminal("c")], ()),
+                rhs(vec![Terminal("b"), Nonterminal("E"), Terminal("d")], ()),
+            ],
+            "E" => vec![
+                rhs(vec![Terminal("e")], ()),
+            ],
+            "F" => vec![
+                rhs(vec![Terminal("e")], ()),
+            ]
+        ],
+        start: "S'",
+    }
+}
+
+#[test]
+fn test_lr1() {
+    let g = lr1_but_not_lalr1_grammar();
+    let c = DefaultConfig::new();
+    // LALR(1) merges the states after `a e` and `b e`, so `E -> e` and `F -> e` conflict
+    match g.lalr1(&c) {
+        Err(LR1Error::Conflict(LR1Conflict::ReduceReduce { r1, r2, .. })) => {
+            assert_eq!((*r1.0, *r2.0), ("F", "E"));
+        }
+        r => panic!("Expected a reduce-reduce conflict, got {:?}", r),
+    }
+    assert_eq!(g.lr0_state_machine().states.len(), 13);
+    // The canonical construction keeps the two states apart, so it has no conflict
+    let pt = g.lr1(&c).unwrap();
+    assert_eq!(pt.states.len(), 14);
+
+    // For an LALR(1) grammar, the canonical table only differs by split states
+    let g = grammar();
+    assert_eq!(g.lalr1(&c).unwrap().states.len(), 10);
+    assert_eq!(g.lr1(&c).unwrap().states.len(), 14);
+}
+
+#[test]
+fn test_minimal_lr1() {
+    let g = lr1_but_not_lalr1_grammar();
+    let c = DefaultConfig::new();
+    let state_machine = g.lr0_state_machine();
+    let pt = g.minimal_lr1(&c).unwrap();
+    assert_eq!(pt.table.states.len(), state_machine.states.len() + 1);
+    let split_states = pt.split_states();
+    assert_eq!(split_states.len(), 1);
+    let (&lr0_state, states) = split_states.iter().next().unwrap();
+    assert_eq!(states.len(), 2);
+    // The split state is the one reached by `e`
+    let (item_set, _) = &state_machine.states[lr0_state];
+    assert!(item_set
+        .items
+        .iter()
+        .all(|item| item.rhs.syms == [Terminal("e")]));
+
+    // Without conflicts caused by merging, the table is the LALR(1) table
+    let g = grammar();
+    let pt = g.minimal_lr1(&c).unwrap();
+    assert!(pt.split_states().is_empty());
+    assert_eq!(pt.table, g.lalr1(&c).unwrap());
+}
+
+#[test]
+fn test_slr1() {
+    let c = DefaultConfig::new();
+    // The LALR(1) grammar is not SLR(1): `=` is in FOLLOW(E), so the state after `V` can reduce
+    // `E -> V` on `=`.
+    let g = grammar();
+    match g.slr1(&c) {
+        Err(LR1Error::Conflict(LR1Conflict::ShiftReduce { token, .. })) => {
+            assert_eq!(token, Some(EQ))
+        }
+        r => panic!("Expected a shift-reduce conflict, got {:?}", r),
+    }
+
+    let g = ambiguous_expression_grammar();
+    let config = TestConfig::new();
+    assert_eq!(g.slr1(&config).unwrap(), g.lalr1(&config).unwrap());
+}
+
+fn lr0_grammar() -> Grammar<&'static str, &'static str, ()> {
+    Grammar {
+        rules: map![
+            "S" => vec![
+                rhs(vec![Nonterminal("P")], ()),
+            ],
+            "P" => vec![
+                rhs(vec![Terminal("("), Nonterminal("P"), Terminal(")")], ()),
+                rhs(vec![Terminal("x")], ()),
+            ]
+        ],
+        start: "S",
+    }
+}
+
+#[test]
+fn test_lr0_table() {
+    let g = lr0_grammar();
+    let c = DefaultConfig::new();
+    let pt = g.lr0_table(&c).unwrap();
+    assert_eq!(pt.states.len(), g.lr0_state_machine().states.len());
+    // The state after `x` reduces regardless of the lookahead
+    let x_state = match pt.states[0].lookahead[&"x"] {
+        LRAction::Shift(s) => s,
+        ref action => panic!("Expected a shift, got {:?}", action),
+    };
+    let reduce_x = LRAction::Reduce(&"P", &g.rules["P"][1]);
+    assert_eq!(pt.states[x_state].eof, Some(reduce_x));
+    assert_eq!(pt.states[x_state].lookahead.len(), 3);
+
+    // The start rule only accepts on EOF, so trailing input is a syntax error
+    let e = pt
+        .parse(vec!["x", ")"], |t| *t, |_| (), |_, _| ())
+        .unwrap_err();
+    assert_eq!(e.token, Some(")"));
+
+    // After `V`, a lookahead is needed to decide whether to reduce `E -> V`
+    let g = grammar();
+    assert!(g.lr0_table(&c).is_err());
+}
+
+struct DeRemerPennelloConfig {
+    shift: bool,
+}
+
+impl<'a> Config<'a, &'a str, &'a str, ()> for DeRemerPennelloConfig {
+    fn resolve_shift_reduce_conflict_in_favor_of_shift(&self) -> bool {
+        self.shift
+    }
+
+    fn lalr1_lookaheads(&self) -> config::LalrLookaheads {
+        config::LalrLookaheads::DeRemerPennello
+    }
+}
+
+#[test]
+fn test_lalr1_deremer_pennello() {
+    let dp = DeRemerPennelloConfig { shift: false };
+    for g in [grammar(), list_grammar(), lr0_grammar()].iter() {
+        assert_eq!(
+            g.lalr1(&dp).unwrap(),
+            g.lalr1(&DefaultConfig::new()).unwrap()
+        );
+    }
+    match lr1_but_not_lalr1_grammar().lalr1(&dp) {
+        Err(LR1Error::Conflict(LR1Conflict::ReduceReduce { .. })) => {}
+        r => panic!("Expected a reduce-reduce conflict, got {:?}", r),
+    }
+
+    let dp = DeRemerPennelloConfig { shift: true };
+    let g = ambiguous_expression_grammar();
+    assert_eq!(g.lalr1(&dp).unwrap(), g.lalr1(&TestConfig::new()).unwrap());
+}
+
+/// Find the state of the table in which the given rule is completed.
+fn completed_in<'a>(
+    state_machine: &LR0StateMachine<'a, &'a str, &'a str, ()>,
+    rhs: &Rhs<&str, &str, ()>,
+) -> usize {
+    state_machine
+        .states
+        .iter()
+        .position(|(iset, _)| {
+            iset.items
+                .iter()
+                .any(|item| std::ptr::eq(item.rhs, rhs) && item.pos == rhs.syms.len())
+        })
+        .unwrap()
+}
+
+#[test]
+fn test_precedence() {
+    let g = Grammar {
+        rules: map![
+            "S" => vec![
+                rhs(vec![Nonterminal("E")], ()),
+            ],
+            "E" => vec![
+                rhs(vec![Nonterminal("E"), Terminal("+"), Nonterminal("E")], ()),
+                rhs(vec![Nonterminal("E"), Terminal("*"), Nonterminal("E")], ()),
+                rhs(vec![Nonterminal("E"), Terminal("^"), Nonterminal("E")], ()),
+                rhs(vec![Nonterminal("E"), Terminal("<"), Nonterminal("E")], ()),
+                rhs(vec![Terminal("-"), Nonterminal("E")], ()),
+                rhs(vec![Terminal("x")], ()),
+            ]
+        ],
+        start: "S",
+    };
+    let c = PrecedenceConfig::new()
+        .nonassoc(vec!["<"])
+        .left(vec!["+", "-"])
+        .left(vec!["*"])
+        .right(vec!["^"])
+        .right(vec!["UMINUS"])
+        .rule_precedence("E", vec![Terminal("-"), Nonterminal("E")], "UMINUS");
+    let pt = g.lalr1(&c).unwrap();
+    let state_machine = g.lr0_state_machine();
+    let e = &g.rules["E"];
+    let state = |rule: usize| &pt.states[completed_in(&state_machine, &e[rule])];
+
+    let plus = state(0);
+    assert_eq!(plus.lookahead[&"+"], LRAction::Reduce(&"E", &e[0]));
+    assert!(matches!(plus.lookahead[&"*"], LRAction::Shift(_)));
+    assert!(matches!(plus.lookahead[&"^"], LRAction::Shift(_)));
+    assert_eq!(plus.lookahead[&"<"], LRAction::Reduce(&"E", &e[0]));
+
+    let power = state(2);
+    assert!(matches!(power.lookahead[&"^"], LRAction::Shift(_)));
+    assert_eq!(power.lookahead[&"*"], LRAction::Reduce(&"E", &e[2]));
+
+    let less = state(3);
+    assert_eq!(less.lookahead[&"<"], LRAction::Error);
+    assert!(matches!(less.lookahead[&"+"], LRAction::Shift(_)));
+
+    let negate = state(4);
+    assert_eq!(negate.lookahead[&"^"], LRAction::Reduce(&"E", &e[4]));
+    assert_eq!(negate.lookahead[&"*"], LRAction::Reduce(&"E", &e[4]));
+
+    // The error of a nonassociative operator survives the default reduction of its state
+    let table = compressed::CompressedParseTable::from(indexed::IndexedParseTable::new(&g, &pt));
+    let parse = |tokens: Vec<&'static str>| table.parse(tokens, |t| *t, |_| (), |_, _| ());
+    assert_eq!(parse(vec!["x", "<", "x"]), Ok(()));
+    let e = parse(vec!["x", "<", "x", "<", "x"]).unwrap_err();
+    assert_eq!(e.token, Some("<"));
+
+    // Without precedence declarations, the conflicts remain
+    assert!(g.lalr1(&PrecedenceConfig::new()).is_err());
+}
+
+struct VetoConfig;
+
+impl<'a> Config<'a, &'a str, &'a str, ()> for VetoConfig {
+    fn reduce_on(&self, rhs: &Rhs<&'a str, &'a str, ()>, lookahead: Option<&&'a str>) -> bool {
+        // Never reduce an `if` statement or an empty statement before `else`
+        lookahead != Some(&"else") || rhs.syms.len() == 7
+    }
+}
+
+#[test]
+fn test_error_action_for_vetoed_reductions() {
+    let g = dangling_else_grammar();
+    let pt = g.lalr1(&VetoConfig).unwrap();
+    let stmt = &g.rules["Stmt"];
+    let state_machine = g.lr0_state_machine();
+    // The shift remains where the reduction was forbidden
+    let if_state = &pt.states[completed_in(&state_machine, &stmt[0])];
+    assert!(matches!(if_state.lookahead[&"else"], LRAction::Shift(_)));
+    assert!(!if_state.is_error(Some(&"else")));
+    // A single forbidden reduction without a conflict leaves the cell empty
+    let shift = |state: usize, token: &str| match pt.states[state].lookahead[&token] {
+        LRAction::Shift(s) => s,
+        ref action => panic!("Expected a shift, got {:?}", action),
+    };
+    let condition = pt.states[shift(shift(0, "if"), "(")].goto[&"Cond"];
+    let after_condition = &pt.states[shift(condition, ")")];
+    assert_eq!(
+        after_condition.eof,
+        Some(LRAction::Reduce(&"Stmt", &stmt[2]))
+    );
+    assert_eq!(after_condition.action(Some(&"else")), None);
+    assert!(after_condition.is_error(Some(&"else")));
+    assert!(after_condition.is_error(Some(&"true")));
+
+    // Conflicting reductions that are all forbidden become an error
+    let g = Grammar {
+        rules: map![
+            "S" => vec![
+                rhs(vec![Nonterminal("P")], ()),
+            ],
+            "P" => vec![
+                rhs(vec![Nonterminal("A"), Terminal("else")], ()),
+                rhs(vec![Nonterminal("B"), Terminal("else")], ()),
+            ],
+            "A" => vec![
+                rhs(vec![Terminal("x")], ()),
+            ],
+            "B" => vec![
+                rhs(vec![Terminal("x")], ()),
+            ]
+        ],
+        start: "S",
+    };
+    let pt = g.lalr1(&VetoConfig).unwrap();
+    let x_state = match pt.states[0].lookahead[&"x"] {
+        LRAction::Shift(s) => s,
+        ref action => panic!("Expected a shift, got {:?}", action),
+    };
+    assert_eq!(
+        pt.states[x_state].action(Some(&"else")),
+        Some(&LRAction::Error)
+    );
+}
+
+#[test]
+fn test_expected_conflicts() {
+    let g = dangling_else_grammar();
+    let c = PrecedenceConfig::new().shift_by_default(true);
+    assert!(g.lalr1(&c).is_ok());
+    // The dangling else is reported twice, but it is one conflict
+    assert!(g.lalr1(&c.clone().expect(1).expect_rr(0)).is_ok());
+    match g.lalr1(&c.clone().expect(0)) {
+        Err(LR1Error::UnexpectedShiftReduceConflicts { expected, found }) => {
+            assert_eq!((expected, found), (0, 1));
+        }
+        r => panic!("Expected unexpected conflicts, got {:?}", r),
+    }
+    match g.lalr1(&c.clone().expect(1).expect_rr(1)) {
+        Err(LR1Error::UnexpectedReduceReduceConflicts { expected, found }) => {
+            assert_eq!((expected, found), (1, 0));
+        }
+        r => panic!("Expected unexpected conflicts, got {:?}", r),
+    }
+
+    // The numbers are checked when collecting conflicts and for GLR tables too
+    let none = c.clone().expect(0);
+    let partial = g.lalr1_collect_conflicts(&none).unwrap_err();
+    assert!(partial.conflicts.is_empty());
+    assert!(matches!(
+        partial.unexpected[..],
+        [LR1Error::UnexpectedShiftReduceConflicts {
+            expected: 0,
+            found: 1
+        }]
+    ));
+    assert!(g.glr(&none).is_err());
+    assert!(g.glr(&c.clone().expect(1)).is_ok());
+
+    // Conflicts resolved by precedence are not counted
+    let g = ambiguous_expression_grammar();
+    let c = PrecedenceConfig::new()
+        .left(vec!["+"])
+        .left(vec!["*"])
+        .expect(0);
+    assert!(g.lalr1(&c).is_ok());
+}
+
+#[test]
+fn test_counterexample() {
+    fn form(derivation: &counterexample::Derivation<&str, &str, ()>) -> String {
+        let syms: Vec<_> = derivation
+            .sentential_form()
+            .into_iter()
+            .map(|sym| sym.to_string())
+            .collect();
+        syms.join(" ")
+    }
+
+    let config = DefaultConfig::new();
+    let g = dangling_else_grammar();
+    let conflict = match g.lalr1(&config) {
+        Err(LR1Error::Conflict(conflict)) => conflict,
+        r => panic!("Expected a conflict, got {:?}", r),
+    };
+    let c = g.counterexample(&conflict).unwrap();
+    assert!(c.unifying);
+    assert!(c
+        .to_string()
+        .starts_with("Example: if ( Cond ) if ( Cond ) Stmt • else Stmt\n"));
+    assert_eq!(c.token, Some(&"else"));
+    assert_eq!(
+        c.prefix,
+        vec![
+            &Terminal("if"),
+            &Terminal("("),
+            &Nonterminal("Cond"),
+            &Terminal(")"),
+            &Nonterminal("Stmt"),
+        ]
+    );
+    assert_eq!(form(&c.first), "if ( Cond ) if ( Cond ) Stmt else Stmt");
+    assert_eq!(
+        c.first.to_string(),
+        "Conflict → [ Stmt → [ if ( Cond ) Stmt → [ if ( Cond ) Stmt • ] else Stmt ] ]"
+    );
+    assert_eq!(
+        c.second.to_string(),
+        "Conflict → [ Stmt → [ if ( Cond ) Stmt → [ if ( Cond ) Stmt • else Stmt ] ] ]"
+    );
+
+    let g = ambiguous_expression_grammar();
+    let partial = g.lalr1_collect_conflicts(&config).unwrap_err();
+    for conflict in partial.conflicts.values().flat_map(|c| c.iter()) {
+        let c = g.counterexample(conflict).unwrap();
+        assert!(c.unifying);
+        assert_eq!(form(&c.first), form(&c.second));
+    }
+
+    // The grammar is unambiguous, so the derivations can't be unified.
+    let g = lr1_but_not_lalr1_grammar();
+    let conflict = match g.lalr1(&config) {
+        Err(LR1Error::Conflict(conflict)) => conflict,
+        r => panic!("Expected a conflict, got {:?}", r),
+    };
+    let c = g.counterexample(&conflict).unwrap();
+    assert!(!c.unifying);
+    let mut forms = vec![form(&c.first), form(&c.second)];
+    forms.sort();
+    assert_eq!(forms, vec!["a e d", "b e d"]);
+}
+
+#[test]
+fn test_conflict_report() {
+    let config = DefaultConfig::new();
+    let g = dangling_else_grammar();
+    let state_machine = g.lr0_state_machine();
+    let conflict = match g.lalr1(&config) {
+        Err(LR1Error::Conflict(conflict)) => conflict,
+        r => panic!("Expected a conflict, got {:?}", r),
+    };
+    assert_eq!(
+        state_machine.report(&conflict).to_string(),
+        "State 8 conflict: shift/reduce on else
+
+    path: if ( Cond ) Stmt
+
+    Stmt → if ( Cond ) Stmt •
+    Stmt → if ( Cond ) Stmt • else Stmt
+
+    else  shift, and go to state 9
+    else  [reduce using Stmt → if ( Cond ) Stmt]
+"
+    );
+
+    let resolved = LR1ResolvedConflict {
+        conflict,
+        applied_resolution: LRConflictResolution::ErrorByNonassociativity,
+    };
+    let report = state_machine.report_resolved(&resolved).to_string();
+    assert!(report.starts_with("State 8 resolved conflict: shift/reduce on else\n"));
+    assert!(report.ends_with(
+        "    else  error (nonassociative)
+    else  [shift, and go to state 9]
+    else  [reduce using Stmt → if ( Cond ) Stmt]
+
+    Conflict resolved as an error (nonassociative).
+"
+    ));
+
+    let g = lr1_but_not_lalr1_grammar();
+    let state_machine = g.lr0_state_machine();
+    let conflict = match g.lalr1(&config) {
+        Err(LR1Error::Conflict(conflict)) => conflict,
+        r => panic!("Expected a conflict, got {:?}", r),
+    };
+    let report = state_machine.report(&conflict).to_string();
+    assert!(report.contains(" conflict: reduce/reduce on d\n"));
+    assert!(report.contains("\n    d  reduce using F → e\n    d  [reduce using E → e]\n"));
+}
+
+fn arithmetic_grammar() -> Grammar<&'static str, &'static str, &'static str> {
+    Grammar {
+        rules: map![
+            "S" => vec![
+                rhs(vec![Nonterminal("E")], "start"),
+            ],
+            "E" => vec![
+                rhs(vec![Nonterminal("E"), Terminal("+"), Nonterminal("T")], "add"),
+                rhs(vec![Nonterminal("T")], "unit"),
+            ],
+            "T" => vec![
+                rhs(vec![Nonterminal("T"), Terminal("*"), Nonterminal("F")], "mul"),
+                rhs(vec![Nonterminal("F")], "unit"),
+            ],
+            "F" => vec![
+                rhs(vec![Terminal("("), Nonterminal("E"), Terminal(")")], "paren"),
+                rhs(vec![Terminal("n")], "unit"),
+            ]
+        ],
+        start: "S",
+    }
+}
+
+fn terminal_of(token: &&'static str) -> &'static str {
+    if token.parse::<i64>().is_ok() {
+        "n"
+    } else {
+        token
+    }
+}
+
+fn evaluate(act: &&str, values: Vec<i64>) -> i64 {
+    match *act {
+        "add" => values[0] + values[2],
+        "mul" => values[0] * values[2],
+        "paren" => values[1],
+        _ => values[0],
+    }
+}
+
+#[test]
+fn test_parse() {
+    let g = arithmetic_grammar();
+    let config = DefaultConfig::new();
+    let table = g.lalr1(&config).unwrap();
+    let parse = |tokens: &[&'static str]| {
+        table.parse(
+            tokens.iter().cloned(),
+            terminal_of,
+            |token| token.parse().unwrap_or(0),
+            evaluate,
+        )
+    };
+    assert_eq!(parse(&["2", "+", "3", "*", "4"]), Ok(14));
+    assert_eq!(parse(&["(", "2", "+", "3", ")", "*", "4"]), Ok(20));
+    assert_eq!(parse(&["7"]), Ok(7));
+
+    let error = parse(&["2", "+", "*", "3"]).unwrap_err();
+    assert_eq!(error.token, Some("*"));
+    assert!(table.states[error.state].is_error(Some(&"*")));
+    assert_eq!(parse(&["(", "2"]).unwrap_err().token, None);
+    assert_eq!(parse(&[]).unwrap_err().state, 0);
+}
+
+#[derive(Debug, PartialEq)]
+enum Ast {
+    Token(&'static str),
+    Num(i64),
+    Binary(Box<Ast>, &'static str, Box<Ast>),
+}
+
+struct AstBuilder {
+    reductions: usize,
+}
+
+impl<'a> parser::Actions<'a, &'static str, &'static str, &'static str> for AstBuilder {
+    type Token = &'static str;
+    type Value = Ast;
+
+    fn terminal(&self, token: &&'static str) -> &'static str {
+        terminal_of(token)
+    }
+
+    fn shift(&mut self, token: &'static str) -> Ast {
+        match token.parse() {
+            Ok(n) => Ast::Num(n),
+            Err(_) => Ast::Token(token),
+        }
+    }
+
+    fn reduce(
+        &mut self,
+        lhs: &&'static str,
+        rhs: &Rhs<&'static str, &'static str, &'static str>,
+        mut children: Vec<Ast>,
+    ) -> Ast {
+        self.reductions += 1;
+        assert_eq!(children.len(), rhs.syms.len());
+        match rhs.act {
+            "add" | "mul" => {
+                let right = children.pop().unwrap();
+                let op = match children.pop() {
+                    Some(Ast::Token(op)) => op,
+                    op => panic!("Expected an operator, got {:?}", op),
+                };
+                assert_eq!(*lhs, if op == "+" { "E" } else { "T" });
+                Ast::Binary(Box::new(children.pop().unwrap()), op, Box::new(right))
+            }
+            "paren" => children.swap_remove(1),
+            _ => children.pop().unwrap(),
+        }
+    }
+}
+
+#[test]
+fn test_parse_with_actions() {
+    let g = arithmetic_grammar();
+    let config = DefaultConfig::new();
+    let table = g.lalr1(&config).unwrap();
+    let mut builder = AstBuilder { reductions: 0 };
+    let ast = table.parse_with(vec!["2", "*", "(", "3", "+", "4", ")"], &mut builder);
+    assert_eq!(
+        ast,
+        Ok(Ast::Binary(
+            Box::new(Ast::Num(2)),
+            "*",
+            Box::new(Ast::Binary(
+                Box::new(Ast::Num(3)),
+                "+",
+                Box::new(Ast::Num(4))
+            ))
+        ))
+    );
+    // F → n three times, T → F three times, E → T twice, and one each for +, ( ) and *
+    assert_eq!(builder.reductions, 11);
+
+    let error = table.parse_with(vec!["2", ")"], &mut builder).unwrap_err();
+    assert_eq!(error.token, Some(")"));
+}
+
+#[test]
+fn test_parse_cst() {
+    let g = arithmetic_grammar();
+    let config = DefaultConfig::new();
+    let table = g.lalr1(&config).unwrap();
+
+    // Tokens with their byte offsets in "12 + 3*4"
+    let tokens = vec![("12", 0), ("+", 3), ("3", 5), ("*", 6), ("4", 7)];
+    let tree = table
+        .parse_cst(
+            tokens.clone(),
+            |&(token, _)| terminal_of(&token),
+            |_, &(token, offset)| offset..offset + token.len(),
+        )
+        .unwrap();
+    assert_eq!(tree.span(), 0..8);
+    assert_eq!(tree.tokens(), tokens.iter().collect::<Vec<_>>());
+    match tree {
+        cst::Cst::Node {
+            lhs, rhs, children, ..
+        } => {
+            assert_eq!((*lhs, rhs.act), ("E", "add"));
+            let spans: Vec<_> = children.iter().map(|c| c.span()).collect();
+            assert_eq!(spans, vec![0..2, 3..4, 5..8]);
+        }
+        tree => panic!("Expected a node, got {:?}", tree),
+    }
+
+    // Spans of token indices
+    let tree = table
+        .parse_cst(vec!["(", "1", ")"], terminal_of, |index, _| {
+            index..index + 1
+        })
+        .unwrap();
+    assert_eq!(tree.span(), 0..3);
+
+    // An empty rule gets an empty span after the preceding token
+    let g = dangling_else_grammar();
+    let config = TestConfig::new();
+    let table = g.lalr1(&config).unwrap();
+    let tree = table
+        .parse_cst(
+            vec!["if", "(", "true", ")"],
+            |&token| token,
+            |index, _| index..index + 1,
+        )
+        .unwrap();
+    match tree {
+        cst::Cst::Node { children, .. } => assert_eq!(children[4].span(), 4..4),
+        tree => panic!("Expected a node, got {:?}", tree),
+    }
+}
+
+fn statement_grammar() -> Grammar<&'static str, &'static str, &'static str> {
+    Grammar {
+        rules: map![
+            "P" => vec![
+                rhs(vec![Nonterminal("L")], "start"),
+            ],
+            "L" => vec![
+                rhs(vec![Nonterminal("L"), Nonterminal("S")], "list"),
+                rhs(vec![Nonterminal("S")], "unit"),
+            ],
+            "S" => vec![
+                rhs(vec![Nonterminal("E"), Terminal(";")], "unit"),
+                rhs(vec![Terminal("error"), Terminal(";")], "error"),
+            ],
+            "E" => vec![
+                rhs(vec![Nonterminal("E"), Terminal("+"), Terminal("n")], "add"),
+                rhs(vec![Terminal("n")], "unit"),
+            ]
+        ],
+        start: "P",
+    }
+}
+
+/// Computes the values of the statements, `None` for a statement with an error.
+#[derive(Default)]
+struct StatementValues {
+    errors: Vec<(usize, Option<&'static str>)>,
+    discarded: Vec<&'static str>,
+    popped: Vec<Vec<Option<i64>>>,
+}
+
+impl<'a> parser::Actions<'a, &'static str, &'static str, &'static str> for StatementValues {
+    type Token = &'static str;
+    type Value = Vec<Option<i64>>;
+
+    fn terminal(&self, token: &&'static str) -> &'static str {
+        terminal_of(token)
+    }
+
+    fn shift(&mut self, token: &'static str) -> Vec<Option<i64>> {
+        token.parse().ok().map(Some).into_iter().collect()
+    }
+
+    fn reduce(
+        &mut self,
+        _lhs: &'a &'static str,
+        rhs: &'a Rhs<&'static str, &'static str, &'static str>,
+        mut children: Vec<Vec<Option<i64>>>,
+    ) -> Vec<Option<i64>> {
+        match rhs.act {
+            "add" => vec![Some(children[0][0].unwrap() + children[2][0].unwrap())],
+            "list" => {
+                let last = children.pop().unwrap();
+                let mut list = children.pop().unwrap();
+                list.extend(last);
+                list
+            }
+            _ => children.swap_remove(0),
+        }
+    }
+}
+
+impl<'a> parser::Recovery<'a, &'static str, &'static str, &'static str> for StatementValues {
+    fn error_terminal(&self) -> &'static str {
+        "error"
+    }
+
+    fn error_value(&mut self, popped: Vec<Vec<Option<i64>>>) -> Vec<Option<i64>> {
+        self.popped.push(popped.into_iter().flatten().collect());
+        vec![None]
+    }
+
+    fn on_syntax_error(&mut self, state: usize, token: Option<&&'static str>) {
+        self.errors.push((state, token.cloned()));
+    }
+
+    fn on_discard(&mut self, token: &'static str) {
+        self.discarded.push(token);
+    }
+}
+
+#[test]
+fn test_error_recovery() {
+    let g = statement_grammar();
+    let config = DefaultConfig::new();
+    let table = g.lalr1(&config).unwrap();
+    let parse = |tokens: &[&'static str]| {
+        let mut actions = StatementValues::default();
+        let r = table.parse_with_recovery(tokens.iter().cloned(), &mut actions);
+        (r, actions)
+    };
+
+    let (r, actions) = parse(&["1", "+", "2", ";", "3", ";"]);
+    assert_eq!(r, Ok(vec![Some(3), Some(3)]));
+    assert!(actions.errors.is_empty());
+
+    let (r, actions) = parse(&["1", ";", "2", "2", ";", "3", ";", ";", "5", "+", "1", ";"]);
+    // The error on the second `;` is found before reducing `S → E ;`. The reduction doesn't
+    // depend on the lookahead, so it is applied and the statement `3 ;` is kept.
+    assert_eq!(r, Ok(vec![Some(1), None, Some(3), None, Some(6)]));
+    let tokens: Vec<_> = actions.errors.iter().map(|&(_, token)| token).collect();
+    assert_eq!(tokens, vec![Some("2"), Some(";")]);
+    for &(state, token) in actions.errors.iter() {
+        assert!(table.states[state].is_error(token.as_ref()));
+    }
+    assert_eq!(actions.discarded, vec!["2"]);
+    assert_eq!(actions.popped, vec![vec![Some(2)], vec![]]);
+
+    // The second error is within three tokens of the first one, so it isn't reported. Its
+    // recovery keeps the statement `error ;` before it.
+    let (r, actions) = parse(&["+", ";", "+", ";", "1", ";"]);
+    assert_eq!(r, Ok(vec![None, None, Some(1)]));
+    assert_eq!(actions.errors.len(), 1);
+
+    // The input ends while discarding tokens.
+    let (r, actions) = parse(&["1", "+"]);
+    assert_eq!(r.unwrap_err().token, None);
+    assert_eq!(actions.errors.len(), 1);
+
+    // Without recovery, the first error ends the parse.
+    let r = table.parse_with(vec!["1", "2", ";"], &mut StatementValues::default());
+    assert_eq!(r.unwrap_err().token, Some("2"));
+
+    // The default reductions recover the same statements.
+    let table = g.lalr1(&config).unwrap().with_default_reductions();
+    let tokens = vec!["1", ";", "2", "2", ";", "3", ";", ";", "5", "+", "1", ";"];
+    let r = table.parse_with_recovery(tokens, &mut StatementValues::default());
+    assert_eq!(r, Ok(vec![Some(1), None, Some(3), None, Some(6)]));
+}
+
+/// Computes the repaired input, and records the repairs.
+struct RepairedInput {
+    repairs: Vec<String>,
+}
+
+impl<'a> parser::Actions<'a, &'static str, &'static str, &'static str> for RepairedInput {
+    type Token = &'static str;
+    type Value = String;
+
+    fn terminal(&self, token: &&'static str) -> &'static str {
+        terminal_of(token)
+    }
+
+    fn shift(&mut self, token: &'static str) -> String {
+        token.to_string()
+    }
+
+    fn reduce(
+        &mut self,
+        _lhs: &'a &'static str,
+        _rhs: &'a Rhs<&'static str, &'static str, &'static str>,
+        children: Vec<String>,
+    ) -> String {
+        children.join(" ")
+    }
+}
+
+impl<'a> repair::Repairer<'a, &'static str, &'static str, &'static str> for RepairedInput {
+    fn insert(&mut self, terminal: &'a &'static str) -> &'static str {
+        if *terminal == "n" {
+            "0"
+        } else {
+            terminal
+        }
+    }
+
+    fn on_repair(
+        &mut self,
+        state: usize,
+        repairs: Vec<repair::Repair<'a, &'static str, &'static str>>,
+    ) {
+        let repairs: Vec<_> = repairs.iter().map(|r| r.to_string()).collect();
+        self.repairs
+            .push(format!("state {}: {}", state, repairs.join(", ")));
+    }
+}
+
+#[test]
+fn test_repair() {
+    let g = arithmetic_grammar();
+    let config = DefaultConfig::new();
+    let table = g.lalr1(&config).unwrap();
+    let parse = |tokens: Vec<&'static str>| {
+        let mut actions = RepairedInput { repairs: vec![] };
+        let r = table.parse_with_repair(tokens, &mut actions);
+        (r, actions.repairs)
+    };
+
+    let (r, repairs) = parse(vec!["1", "+", "*", "2"]);
+    assert_eq!(r, Ok("1 + 2".to_string()));
+    assert_eq!(repairs.len(), 1);
+    assert!(repairs[0].ends_with(": deleted `*`"));
+
+    let (r, repairs) = parse(vec!["(", "1", "+", "2"]);
+    assert_eq!(r, Ok("( 1 + 2 )".to_string()));
+    assert!(repairs[0].ends_with(": inserted `)`"));
+
+    let (r, repairs) = parse(vec!["1", "*", "2", "3", "+", "4", ")"]);
+    assert_eq!(r, Ok("1 * 2 * 3 + 4".to_string()));
+    assert_eq!(repairs.len(), 2);
+    assert!(repairs[0].ends_with(": inserted `*`"));
+    assert!(repairs[1].ends_with(": deleted `)`"));
+
+    // The repairs are the same on each parse.
+    assert_eq!(parse(vec!["1", "2", "3"]), parse(vec!["1", "2", "3"]));
+
+    // Closing the parentheses would cost more than the maximal cost.
+    let (r, repairs) = parse(vec!["(", "(", "(", "("]);
+    assert_eq!(r.unwrap_err().token, None);
+    assert!(repairs.is_empty());
+}
+
+#[test]
+fn test_expected() {
+    let g = arithmetic_grammar();
+    let config = DefaultConfig::new();
+    let table = g.lalr1(&config).unwrap();
+    let error = |tokens: Vec<&'static str>| {
+        table
+            .parse(tokens, terminal_of, |_| (), |_, _| ())
+            .unwrap_err()
+    };
+
+    let expected: BTreeSet<_> = coll![Some(&"("), Some(&"n")];
+    assert_eq!(table.states[0].expected(), expected);
+
+    // After `1`, the state also expects `)`, but only within parentheses.
+    let e = error(vec!["1", "2"]);
+    assert_eq!(e.stack.last(), Some(&e.state));
+    let expected: BTreeSet<_> = coll![None, Some(&")"), Some(&"*"), Some(&"+")];
+    assert_eq!(table.states[e.state].expected(), expected);
+    let expected: BTreeSet<_> = coll![None, Some(&"*"), Some(&"+")];
+    assert_eq!(table.expected_on_stack(&e), expected);
+    let e = error(vec!["(", "1", "2"]);
+    let expected: BTreeSet<_> = coll![Some(&")"), Some(&"*"), Some(&"+")];
+    assert_eq!(table.expected_on_stack(&e), expected);
+}
+
+/// Evaluates arithmetic expressions, and counts the reductions.
+struct Calculator {
+    reductions: usize,
+}
+
+impl<'a> parser::Actions<'a, &'static str, &'static str, &'static str> for Calculator {
+    type Token = &'static str;
+    type Value = i64;
+
+    fn terminal(&self, token: &&'static str) -> &'static str {
+        terminal_of(token)
+    }
+
+    fn shift(&mut self, token: &'static str) -> i64 {
+        token.parse().unwrap_or(0)
+    }
+
+    fn reduce(
+        &mut self,
+        _lhs: &'a &'static str,
+        rhs: &'a Rhs<&'static str, &'static str, &'static str>,
+        children: Vec<i64>,
+    ) -> i64 {
+        self.reductions += 1;
+        evaluate(&rhs.act, children)
+    }
+}
+
+#[test]
+fn test_push_parser() {
+    let g = arithmetic_grammar();
+    let config = DefaultConfig::new();
+    let table = g.lalr1(&config).unwrap();
+    let mut parser = push::PushParser::new(&table, Calculator { reductions: 0 });
+
+    for &token in ["2", "*", "(", "3"].iter() {
+        parser.feed(token).unwrap();
+    }
+    let checkpoint = parser.snapshot();
+    let stack = checkpoint.states();
+    for &token in ["+", "4", ")"].iter() {
+        parser.feed(token).unwrap();
+    }
+    assert_eq!(parser.finish(), Ok(14));
+    assert_eq!(parser.state(), 0);
+
+    // Roll back to the checkpoint, whose frames are shared with the parse above.
+    parser.restore(checkpoint.clone());
+    assert_eq!(parser.state(), checkpoint.state());
+    parser.feed(")").unwrap();
+    assert_eq!(parser.finish(), Ok(6));
+
+    // A rejected token leaves the parser unchanged, even if the table reduces before the error.
+    parser.feed("2").unwrap();
+    let reductions = parser.actions().reductions;
+    let e = parser.feed(")").unwrap_err();
+    assert_eq!(e.token, Some(")"));
+    assert_eq!(e.stack, parser.snapshot().states());
+    assert_eq!(parser.actions().reductions, reductions);
+    let expected: BTreeSet<_> = coll![None, Some(&"*"), Some(&"+")];
+    assert_eq!(parser.expected(), expected);
+    assert_eq!(parser.finish(), Ok(2));
+
+    parser.feed("1").unwrap();
+    parser.feed("+").unwrap();
+    assert_eq!(parser.finish().unwrap_err().token, None);
+    parser.feed("1").unwrap();
+    assert_eq!(parser.finish(), Ok(2));
+
+    // The checkpoint is still intact.
+    assert_eq!(checkpoint.states(), stack);
+}
+
+#[test]
+fn test_glr_table() {
+    let g = ambiguous_expression_grammar();
+    let c = DefaultConfig::new();
+    let table = g.glr(&c).unwrap();
+    assert_eq!(table.conflicts(), 4);
+    for state in table.states.iter() {
+        for actions in state.lookahead.values().filter(|actions| actions.len() > 1) {
+            match actions[..] {
+                [LRAction::Shift(_), LRAction::Reduce(&"E", _)] => {}
+                ref actions => panic!("Expected a shift and a reduction, got {:?}", actions),
+            }
+        }
+    }
+
+    // The configuration still resolves the conflicts it can.
+    let c = PrecedenceConfig::new().left(vec!["+"]);
+    let table = g.glr(&c).unwrap();
+    // Only the conflict between `E + E` and `+` has precedences
+    assert_eq!(table.conflicts(), 3);
+    let c = PrecedenceConfig::new().left(vec!["+"]).left(vec!["*"]);
+    let table = g.glr(&c).unwrap();
+    assert_eq!(table.conflicts(), 0);
+    assert_eq!(table, glr::GLRParseTable::from(g.lalr1(&c).unwrap()));
+
+    let g = lr1_but_not_lalr1_grammar();
+    let c = DefaultConfig::new();
+    let table = g.glr(&c).unwrap();
+    assert_eq!(table.conflicts(), 2);
+    let state = table
+        .states
+        .iter()
+        .find(|state| state.actions(Some(&"d")).len() > 1)
+        .unwrap();
+    match *state.actions(Some(&"d")) {
+        [LRAction::Reduce(&"F", _), LRAction::Reduce(&"E", _)] => {}
+        ref actions => panic!("Expected two reductions, got {:?}", actions),
+    }
+    assert!(state.actions(Some(&"a")).is_empty());
+}
+
+/// Write a tree with parentheses around the nodes with more than one child.
+fn bracket<T, N, A>(tree: &cst::Cst<T, N, A, &&'static str>) -> String {
+    match *tree {
+        cst::Cst::Token { token, .. } => token.to_string(),
+        cst::Cst::Node { ref children, .. } => {
+            let children: Vec<_> = children.iter().map(bracket).collect();
+            if children.len() == 1 {
+                children[0].clone()
+            } else {
+                format!("({})", children.join(" "))
+            }
+        }
+    }
+}
+
+#[test]
+fn test_glr_parse() {
+    let g = ambiguous_expression_grammar();
+    let c = DefaultConfig::new();
+    let table = g.glr(&c).unwrap();
+    let parse = |tokens: Vec<&'static str>| table.parse(tokens, |&t| t);
+
+    let forest = parse(vec!["x"]).unwrap();
+    assert_eq!(forest.count(), Some(1));
+    let forest = parse(vec!["x", "+", "x", "*", "x"]).unwrap();
+    assert_eq!(forest.count(), Some(2));
+    let trees: BTreeSet<_> = forest.trees().iter().map(bracket).collect();
+    let expected: BTreeSet<_> = coll!["((x + x) * x)".to_string(), "(x + (x * x))".to_string()];
+    assert_eq!(trees, expected);
+    assert_eq!(forest.span(forest.root()), 0..5);
+
+    // Choose the derivations with an atomic right operand, making the operators left-associative
+    // with the same precedence.
+    let tree = forest.disambiguate(|node| {
+        node.families
+            .iter()
+            .position(|family| forest.span(family.children[2]).len() == 1)
+            .unwrap()
+    });
+    assert_eq!(bracket(&tree), "((x + x) * x)");
+    assert_eq!(tree.span(), 0..5);
+
+    // The number of trees is a Catalan number, but the forest stays small.
+    let forest = parse(vec!["x", "+", "x", "+", "x", "+", "x", "+", "x"]).unwrap();
+    assert_eq!(forest.count(), Some(14));
+    assert_eq!(forest.trees().len(), 14);
+    assert!(forest.nodes().len() <= 15);
+
+    let e = parse(vec!["x", "+", "+"]).unwrap_err();
+    assert_eq!(e.token, Some("+"));
+    let e = parse(vec!["x", "+"]).unwrap_err();
+    assert_eq!(e.token, None);
+    assert_eq!(e.states.len(), 1);
+}
+
+#[test]
+fn test_glr_parse_empty_rules() {
+    // `B → ε` conflicts with shifting `a`, and the parser has to follow both.
+    let g = Grammar {
+        rules: map![
+            "S" => vec![
+                rhs(vec![Nonterminal("A")], ()),
+            ],
+            "A" => vec![
+                rhs(vec![Nonterminal("B"), Nonterminal("A"), Terminal("c")], ()),
+                rhs(vec![Terminal("a")], ()),
+            ],
+            "B" => vec![
+                rhs(vec![Terminal("b")], ()),
+                rhs(vec![], ()),
+            ]
+        ],
+        start: "S",
+    };
+    let c = DefaultConfig::new();
+    let table = g.glr(&c).unwrap();
+    assert!(table.conflicts() > 0);
+    let parse = |tokens: Vec<&'static str>| table.parse(tokens, |&t| t).map(|f| f.count());
+    assert_eq!(parse(vec!["a"]), Ok(Some(1)));
+    assert_eq!(parse(vec!["a", "c", "c"]), Ok(Some(1)));
+    assert_eq!(parse(vec!["b", "a", "c"]), Ok(Some(1)));
+    assert_eq!(parse(vec!["b", "b", "a", "c", "c"]), Ok(Some(1)));
+    assert!(parse(vec!["b", "a"]).is_err());
+
+    // `L → L L` and `L → ε` lead to infinitely many trees.
+    let g = Grammar {
+        rules: map![
+            "S" => vec![
+                rhs(vec![Nonterminal("P")], ()),
+            ],
+            "P" => vec![
+                rhs(vec![Nonterminal("L"), Terminal(".")], ()),
+            ],
+            "L" => vec![
+                rhs(vec![Nonterminal("L"), Nonterminal("L")], ()),
+                rhs(vec![Terminal("a")], ()),
+                rhs(vec![], ()),
+            ]
+        ],
+        start: "S",
+    };
+    let table = g.glr(&c).unwrap();
+    let forest = table.parse(vec!["a", "a", "."], |&t| t).unwrap();
+    assert_eq!(forest.count(), None);
+    let trees: BTreeSet<_> = forest.trees().iter().map(bracket).collect();
+    assert!(trees.contains("((a a) .)"));
+}
+
+#[test]
+fn test_default_reductions() {
+    let g = arithmetic_grammar();
+    let config = DefaultConfig::new();
+    let full = g.lalr1(&config).unwrap();
+    let table = g.lalr1(&config).unwrap().with_default_reductions();
+    let entries = |table: &LR1ParseTable<_, _, _>| {
+        table
+            .states
+            .iter()
+            .map(|state| state.lookahead.len() + state.eof.iter().count())
+            .sum::<usize>()
+    };
+    assert!(entries(&table.table) < entries(&full));
+    // F → n •, F → ( E ) •, T → F • and T → T * F •
+    let consistent = (0..full.states.len()).filter(|&s| table.is_consistent(s));
+    assert_eq!(consistent.count(), 4);
+    for (i, state) in full.states.iter().enumerate() {
+        for t in state.expected() {
+            assert_eq!(table.action(i, t), state.action(t));
+        }
+    }
+
+    let parse = |tokens: Vec<&'static str>| {
+        table.parse(
+            tokens,
+            terminal_of,
+            |token| token.parse().unwrap_or(0),
+            evaluate,
+        )
+    };
+    assert_eq!(parse(vec!["2", "*", "(", "3", "+", "4", ")"]), Ok(14));
+    // The error is still detected before the token is shifted.
+    let e = parse(vec!["2", "*", ")"]).unwrap_err();
+    assert_eq!(e.token, Some(")"));
+    assert_eq!(table.expected_on_stack(&e), full.expected_on_stack(&e));
+    // The other drivers run on the table too.
+    let tree = table
+        .parse_cst(vec!["2", "*", "3"], terminal_of, |index, _| {
+            index..index + 1
+        })
+        .unwrap();
+    assert_eq!(tree.span(), 0..3);
+    let mut actions = RepairedInput { repairs: vec![] };
+    let r = table.parse_with_repair(vec!["1", "+", "*", "2"], &mut actions);
+    assert_eq!(r, Ok("1 + 2".to_string()));
+    let mut parser =
+        push::PushParser::with_default_reductions(&table, Calculator { reductions: 0 });
+    let mut full_parser = push::PushParser::new(&full, Calculator { reductions: 0 });
+    for &token in ["2", "*"].iter() {
+        parser.feed(token).unwrap();
+        full_parser.feed(token).unwrap();
+    }
+    assert_eq!(parser.expected(), full_parser.expected());
+    let state = parser.state();
+    assert_eq!(parser.feed(")").unwrap_err().token, Some(")"));
+    assert_eq!(parser.state(), state);
+    for &token in ["(", "3", "+", "4", ")"].iter() {
+        parser.feed(token).unwrap();
+    }
+    assert_eq!(parser.finish(), Ok(14));
+    // The default reduction replaces the errors of its state.
+    let n = match full.states[0].action(Some(&"n")) {
+        Some(&LRAction::Shift(target)) => target,
+        _ => unreachable!(),
+    };
+    assert!(full.states[n].is_error(Some(&"(")));
+    assert!(!table.is_error(n, Some(&"(")));
+
+    // The consistent states reduce `2` to a `T` before the next token is read.
+    let reductions = std::cell::Cell::new(0);
+    let reads = std::cell::RefCell::new(vec![]);
+    let tokens = vec!["2", "+", "3"]
+        .into_iter()
+        .inspect(|_| reads.borrow_mut().push(reductions.get()));
+    let value = table.parse(
+        tokens,
+        terminal_of,
+        |token| token.parse().unwrap_or(0),
+        |act, children| {
+            reductions.set(reductions.get() + 1);
+            evaluate(act, children)
+        },
+    );
+    assert_eq!(value, Ok(5));
+    assert_eq!(*reads.borrow(), vec![0, 2, 3]);
+}
+
+#[test]
+fn test_eliminate_unit_rules() {
+    let g = arithmetic_grammar();
+    let config = DefaultConfig::new();
+    let full = g.lalr1(&config).unwrap();
+    let mut table = g.lalr1(&config).unwrap();
+    table.eliminate_unit_rules(|&act| act == "unit");
+
+    let mut full_calculator = Calculator { reductions: 0 };
+    let mut calculator = Calculator { reductions: 0 };
+    let tokens = vec!["2", "*", "(", "3", "+", "4", ")"];
+    assert_eq!(
+        full.parse_with(tokens.clone(), &mut full_calculator),
+        Ok(14)
+    );
+    assert_eq!(table.parse_with(tokens, &mut calculator), Ok(14));
+    // The three reductions of an `F` to a `T` are bypassed. `E → T` is not, since its state also
+    // shifts `*`.
+    assert_eq!(full_calculator.reductions, 11);
+    assert_eq!(calculator.reductions, 8);
+
+    // The language is the same.
+    let inputs = vec![
+        vec!["2"],
+        vec!["(", "2", ")", "*", "3"],
+        vec!["2", "3"],
+        vec!["(", "2"],
+        vec!["2", "*", ")"],
+        vec!["2", "+", "+"],
+        vec![")"],
+    ];
+    for tokens in inputs {
+        let accepted = |table: &LR1ParseTable<_, _, _>| {
+            let mut calculator = Calculator { reductions: 0 };
+            table.parse_with(tokens.clone(), &mut calculator).ok()
+        };
+        assert_eq!(accepted(&table), accepted(&full));
+    }
+
+    // The unit rules are bypassed before adding default reductions.
+    let mut table = g.lalr1(&config).unwrap();
+    table.eliminate_unit_rules(|&act| act == "unit");
+    let table = table.with_default_reductions();
+    let mut calculator = Calculator { reductions: 0 };
+    assert_eq!(
+        table.parse_with(vec!["2", "*", "3"], &mut calculator),
+        Ok(6)
+    );
+    assert_eq!(calculator.reductions, 4);
+}
+
+#[test]
+fn test_indexed_table() {
+    fn table() -> indexed::IndexedParseTable<&'static str, &'static str, &'static str> {
+        let g = arithmetic_grammar();
+        let config = DefaultConfig::new();
+        let table = g.lalr1(&config).unwrap();
+        indexed::IndexedParseTable::new(&g, &table)
+    }
+    fn assert_send<T: Send + 'static>(_: &T) {}
+
+    // The table outlives the grammar.
+    let table = table();
+    assert_send(&table);
+    assert_eq!(table.terminals, vec!["(", ")", "*", "+", "n"]);
+    assert_eq!(table.nonterminals, vec!["E", "F", "T"]);
+    let acts: Vec<_> = table.rules.iter().map(|rule| rule.act).collect();
+    assert_eq!(acts, vec!["add", "unit", "paren", "unit", "mul", "unit"]);
+    assert_eq!(table.rules[4].len, 3);
+    assert_eq!(table.action(0, None), None);
+    let n = table.terminal_index(&"n");
+    assert!(matches!(
+        table.action(0, n),
+        Some(indexed::IndexedAction::Shift(_))
+    ));
+
+    let parse = |tokens: Vec<&'static str>| {
+        table.parse(
+            tokens,
+            terminal_of,
+            |token| token.parse().unwrap_or(0),
+            evaluate,
+        )
+    };
+    assert_eq!(parse(vec!["2", "*", "(", "3", "+", "4", ")"]), Ok(14));
+    let e = parse(vec!["2", "?"]).unwrap_err();
+    assert_eq!(e.token, Some("?"));
+    assert_eq!(e.stack.len(), 2);
+}
+
+#[test]
+fn test_validate_indexed_table() {
+    let g = arithmetic_grammar();
+    let config = DefaultConfig::new();
+    let table = indexed::IndexedParseTable::new(&g, &g.lalr1(&config).unwrap());
+    assert_eq!(table.validate(&g), Ok(()));
+
+    let mut other = arithmetic_grammar();
+    other.rules.get_mut("T").unwrap()[0].act = "div";
+    assert_eq!(table.validate(&other), Err(indexed::TableMismatch::Rule(4)));
+    let other = statement_grammar();
+    assert_eq!(
+        table.validate(&other),
+        Err(indexed::TableMismatch::Terminal(0))
+    );
+
+    let mut broken = table.clone();
+    broken.actions[1][0] = Some(indexed::IndexedAction::Shift(broken.len()));
+    assert_eq!(broken.validate(&g), Err(indexed::TableMismatch::State(1)));
+    let mut other = arithmetic_grammar();
+    other.rules.get_mut("E").unwrap()[0].syms.swap(0, 2);
+    assert_eq!(table.validate(&other), Err(indexed::TableMismatch::Rule(0)));
+    // The grammar has more terminals, nonterminals or rules than the table.
+    let mut other = arithmetic_grammar();
+    other
+        .rules
+        .get_mut("F")
+        .unwrap()
+        .push(rhs(vec![Terminal("-"), Nonterminal("F")], "neg"));
+    assert_eq!(
+        table.validate(&other),
+        Err(indexed::TableMismatch::Terminal(4))
+    );
+    let mut other = arithmetic_grammar();
+    other
+        .rules
+        .insert("G", vec![rhs(vec![Terminal("n")], "unit")]);
+    assert_eq!(
+        table.validate(&other),
+        Err(indexed::TableMismatch::Nonterminal(2))
+    );
+    let mut other = arithmetic_grammar();
+    other
+        .rules
+        .get_mut("T")
+        .unwrap()
+        .push(rhs(vec![Terminal("n")], "unit"));
+    assert_eq!(table.validate(&other), Err(indexed::TableMismatch::Rule(6)));
+
+    let mut broken = table.clone();
+    broken.rules.swap(0, 1);
+    assert_eq!(broken.validate(&g), Err(indexed::TableMismatch::Rule(0)));
+}
+
+#[test]
+fn test_codegen() {
+    let g = arithmetic_grammar();
+    let config = DefaultConfig::new();
+    let table = g.lalr1(&config).unwrap();
+    let mut source = String::new();
+    codegen::write_parse_table(&mut source, "arithmetic", &g, &table).unwrap();
+    // The integration test `tests/codegen.rs` compiles this snapshot, and CI checks that it is up
+    // to date.
+    let snapshot =
+        std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/arithmetic.rs");
+    std::fs::write(snapshot, &source).unwrap();
+    assert!(source.contains("pub mod arithmetic {"));
+    assert!(source.contains(r#"terminals: &["(", ")", "*", "+", "n"],"#));
+    assert!(source.contains(r#"nonterminals: &["E", "F", "T"],"#));
+    assert!(source.contains("StaticRule { lhs: 2, len: 3 }, // 4: T → T * F\n"));
+    assert!(source.contains("Some(Accept),"));
+
+    // The actions are not written, so they needn't be `Clone`.
+    #[derive(Debug)]
+    struct Act;
+    let other = Grammar {
+        rules: map![
+            "S" => vec![rhs(vec![Nonterminal("A")], Act)],
+            "A" => vec![rhs(vec![Terminal("a")], Act)]
+        ],
+        start: "S",
+    };
+    let other_config = DefaultConfig::new();
+    let other_table = other.lalr1(&other_config).unwrap();
+    let mut other_source = String::new();
+    codegen::write_parse_table(&mut other_source, "other", &other, &other_table).unwrap();
+    assert!(other_source.contains("StaticRule { lhs: 0, len: 1 }, // 0: A → a\n"));
+
+    // The runtime on the arrays the source holds.
+    let indexed = indexed::IndexedParseTable::new(&g, &table);
+    let rules: Vec<_> = indexed
+        .rules
+        .iter()
+        .map(|rule| codegen::StaticRule {
+            lhs: rule.lhs,
+            len: rule.len,
+        })
+        .collect();
+    let actions: Vec<_> = indexed.actions.concat();
+    let gotos: Vec<_> = indexed.gotos.concat();
+    let table = codegen::StaticParseTable {
+        terminals: &["(", ")", "*", "+", "n"],
+        nonterminals: &["E", "F", "T"],
+        rules: &rules,
+        actions: &actions,
+        gotos: &gotos,
+    };
+    assert_eq!(table.len(), indexed.len());
+    assert_eq!(source.matches("// state").count(), table.len());
+    let parse = |tokens: Vec<&'static str>| {
+        table.parse(
+            tokens,
+            |token| table.terminal_index(terminal_of(token)),
+            |token| token.parse().unwrap_or(0),
+            |rule, values| evaluate(&indexed.rules[rule].act, values),
+        )
+    };
+    assert_eq!(parse(vec!["2", "*", "(", "3", "+", "4", ")"]), Ok(14));
+    let e = parse(vec!["2", "?"]).unwrap_err();
+    assert_eq!(e.token, Some("?"));
+    assert_eq!(e.stack.len(), 2);
+}
+
+#[test]
+fn test_compressed_table() {
+    let g = arithmetic_grammar();
+    let config = DefaultConfig::new();
+    let indexed = indexed::IndexedParseTable::new(&g, &g.lalr1(&config).unwrap());
+    let table = compressed::CompressedParseTable::from(indexed.clone());
+    assert_eq!(table.len(), indexed.len());
+    for state in 0..indexed.len() {
+        for (t, &action) in indexed.actions[state].iter().enumerate() {
+            let t = if t < indexed.terminals.len() {
+                Some(t)
+            } else {
+                None
+            };
+            match action {
+                Some(action) => assert_eq!(table.action(state, t), action),
+                // The errors of a state are replaced by its default reduction.
+                None => assert!(matches!(
+                    table.action(state, t),
+                    indexed::IndexedAction::Error | indexed::IndexedAction::Reduce(_)
+                )),
+            }
+        }
+        for (n, &goto) in indexed.gotos[state].iter().enumerate() {
+            if let Some(target) = goto {
+                assert_eq!(table.goto(state, n), target);
+            }
+        }
+    }
+    let compression = table.compression();
+    assert!(compression.ratio() < 1.0);
+    assert!(compression
+        .to_string()
+        .starts_with(&format!("{} entries compressed to ", compression.dense)));
+
+    let parse = |tokens: Vec<&'static str>| {
+        table.parse(
+            tokens,
+            terminal_of,
+            |token| token.parse().unwrap_or(0),
+            evaluate,
+        )
+    };
+    assert_eq!(parse(vec!["2", "*", "(", "3", "+", "4", ")"]), Ok(14));
+    let e = parse(vec!["2", "?"]).unwrap_err();
+    assert_eq!(e.token, Some("?"));
+    let e = parse(vec!["2", "*", ")"]).unwrap_err();
+    assert_eq!(e.token, Some(")"));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde() {
+    let json = serde_json::to_string(&arithmetic_grammar()).unwrap();
+    let g: Grammar<String, String, String> = serde_json::from_str(&json).unwrap();
+    assert_eq!(g.rules["E"][0].syms[1], Terminal("+".to_string()));
+    let config = DefaultConfig::new();
+    let table = indexed::IndexedParseTable::new(&g, &g.lalr1(&config).unwrap());
+
+    let json = serde_json::to_string(&table).unwrap();
+    let loaded: indexed::IndexedParseTable<String, String, String> =
+        serde_json::from_str(&json).unwrap();
+    assert_eq!(loaded, table);
+    assert_eq!(loaded.validate(&g), Ok(()));
+    let value = loaded.parse(
+        vec!["2", "*", "(", "3", "+", "4", ")"],
+        |token| terminal_of(token).to_string(),
+        |token| token.parse().unwrap_or(0),
+        |act, values| evaluate(&act.as_str(), values),
+    );
+    assert_eq!(value, Ok(14));
+}
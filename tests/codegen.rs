@@ -0,0 +1,39 @@
+extern crate lalry;
+
+// Written by `test_codegen` in `src/tests.rs` for the arithmetic grammar, with the start rule
+// `S → E`.
+include!("fixtures/arithmetic.rs");
+
+fn terminal_of(token: &str) -> &str {
+    if token.parse::<i64>().is_ok() {
+        "n"
+    } else {
+        token
+    }
+}
+
+#[test]
+fn test_generated_table() {
+    let table = &arithmetic::TABLE;
+    assert_eq!(table.len(), 12);
+    let parse = |tokens: Vec<&'static str>| {
+        table.parse(
+            tokens,
+            |token| table.terminal_index(terminal_of(token)),
+            |token| token.parse().unwrap_or(0),
+            |rule, values: Vec<i64>| match rule {
+                // E → E + T
+                0 => values[0] + values[2],
+                // T → T * F
+                4 => values[0] * values[2],
+                // F → ( E )
+                2 => values[1],
+                _ => values[0],
+            },
+        )
+    };
+    assert_eq!(parse(vec!["2", "*", "(", "3", "+", "4", ")"]), Ok(14));
+    let e = parse(vec!["2", "?"]).unwrap_err();
+    assert_eq!(e.token, Some("?"));
+    assert_eq!(parse(vec!["(", "1"]).unwrap_err().token, None);
+}
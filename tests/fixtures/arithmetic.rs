@@ -0,0 +1,60 @@
+/// The parse table generated by lalry.
+#[allow(dead_code)]
+pub mod arithmetic {
+    use ::lalry::codegen::{StaticParseTable, StaticRule};
+    use ::lalry::indexed::IndexedAction::*;
+
+    /// The parse table.
+    pub static TABLE: StaticParseTable<'static> = StaticParseTable {
+        terminals: &["(", ")", "*", "+", "n"],
+        nonterminals: &["E", "F", "T"],
+        rules: &[
+            StaticRule { lhs: 0, len: 3 }, // 0: E → E + T
+            StaticRule { lhs: 0, len: 1 }, // 1: E → T
+            StaticRule { lhs: 1, len: 3 }, // 2: F → ( E )
+            StaticRule { lhs: 1, len: 1 }, // 3: F → n
+            StaticRule { lhs: 2, len: 3 }, // 4: T → T * F
+            StaticRule { lhs: 2, len: 1 }, // 5: T → F
+        ],
+        actions: &[
+            // state 0
+            Some(Shift(1)), None, None, None, Some(Shift(2)), None,
+            // state 1
+            Some(Shift(1)), None, None, None, Some(Shift(2)), None,
+            // state 2
+            None, Some(Reduce(3)), Some(Reduce(3)), Some(Reduce(3)), None, Some(Reduce(3)),
+            // state 3
+            None, None, None, Some(Shift(7)), None, Some(Accept),
+            // state 4
+            None, Some(Reduce(5)), Some(Reduce(5)), Some(Reduce(5)), None, Some(Reduce(5)),
+            // state 5
+            None, Some(Reduce(1)), Some(Shift(8)), Some(Reduce(1)), None, Some(Reduce(1)),
+            // state 6
+            None, Some(Shift(9)), None, Some(Shift(7)), None, None,
+            // state 7
+            Some(Shift(1)), None, None, None, Some(Shift(2)), None,
+            // state 8
+            Some(Shift(1)), None, None, None, Some(Shift(2)), None,
+            // state 9
+            None, Some(Reduce(2)), Some(Reduce(2)), Some(Reduce(2)), None, Some(Reduce(2)),
+            // state 10
+            None, Some(Reduce(0)), Some(Shift(8)), Some(Reduce(0)), None, Some(Reduce(0)),
+            // state 11
+            None, Some(Reduce(4)), Some(Reduce(4)), Some(Reduce(4)), None, Some(Reduce(4)),
+        ],
+        gotos: &[
+            Some(3), Some(4), Some(5),
+            Some(6), Some(4), Some(5),
+            None, None, None,
+            None, None, None,
+            None, None, None,
+            None, None, None,
+            None, None, None,
+            None, Some(4), Some(10),
+            None, Some(11), None,
+            None, None, None,
+            None, None, None,
+            None, None, None,
+        ],
+    };
+}